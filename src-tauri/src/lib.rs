@@ -1,18 +1,58 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 use std::sync::Mutex;
-use tauri::{Manager, WebviewUrl, WebviewWindowBuilder};
+use tauri::{Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
 use uuid::Uuid;
 use discord_rich_presence::{activity, DiscordIpc, DiscordIpcClient};
 
 // Discord Application ID - replace with your actual ID from Discord Developer Portal
 const DISCORD_APP_ID: &str = "1448073023348539495";
 
+// Windows showing the app's own manager UI. Only these may import files by path, GeoGuessr
+// windows run remote pages that must not be able to read local files
+const MANAGER_WINDOW_LABELS: &[&str] = &["main", "manager"];
+
 // GeoGuessr Event Framework URL - always loaded for Discord presence
 const GEF_URL: &str = "https://miraclewhips.dev/geoguessr-event-framework/geoguessr-event-framework.min.js";
 
+// GM functions a script can be granted with @grant (unsafeWindow, GM_info and GM.info are always available)
+const GM_API_NAMES: &[&str] = &[
+    "GM_getValue",
+    "GM_setValue",
+    "GM_deleteValue",
+    "GM_listValues",
+    "GM_addValueChangeListener",
+    "GM_removeValueChangeListener",
+    "GM_addStyle",
+    "GM_xmlhttpRequest",
+    "GM_openInTab",
+    "GM_getResourceText",
+    "GM_getResourceURL",
+    "GM_registerMenuCommand",
+    "GM_unregisterMenuCommand",
+    "GM_notification",
+    "GM_setClipboard",
+    "GM_download",
+    "GM.getValue",
+    "GM.setValue",
+    "GM.deleteValue",
+    "GM.listValues",
+    "GM.addValueChangeListener",
+    "GM.removeValueChangeListener",
+    "GM.addStyle",
+    "GM.xmlHttpRequest",
+    "GM.openInTab",
+    "GM.getResourceText",
+    "GM.getResourceUrl",
+    "GM.registerMenuCommand",
+    "GM.unregisterMenuCommand",
+    "GM.notification",
+    "GM.setClipboard",
+    "GM.download",
+];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct UserScript {
     id: String,
@@ -30,11 +70,120 @@ struct UserScript {
     #[serde(default)]
     author: Option<String>,
     #[serde(default)]
+    namespace: Option<String>,
+    #[serde(default)]
     requires: Vec<String>,
     #[serde(default)]
+    resources: Vec<ScriptResource>,
+    #[serde(default)]
+    matches: Vec<String>,
+    #[serde(default)]
+    includes: Vec<String>,
+    #[serde(default)]
+    excludes: Vec<String>,
+    #[serde(default)]
+    exclude_matches: Vec<String>,
+    #[serde(default)]
+    run_at: Option<String>,
+    #[serde(default)]
+    grants: Vec<String>,
+    #[serde(default)]
+    connects: Vec<String>,
+    // File on disk the script is kept in sync with while it's being developed
+    #[serde(default)]
+    local_path: Option<String>,
+    // Hosts the user allowed for gm_xhr beyond the script's @connect list
+    #[serde(default)]
+    user_connects: Vec<String>,
+    #[serde(default)]
     last_updated: Option<u64>,
     #[serde(default)]
     last_fetch_error: Option<String>,
+    // ETag and Last-Modified of the response `code` came from, cleared once the code is edited
+    #[serde(default)]
+    etag: Option<String>,
+    #[serde(default)]
+    last_modified: Option<String>,
+    // Newer code found by an update check, only run once the user accepts it
+    #[serde(default)]
+    pending_update: Option<PendingUpdate>,
+    // SHA-256 of the last update the user rejected, so checks don't offer it again
+    #[serde(default)]
+    rejected_update: Option<String>,
+    // Kept at its current version, auto_update_scripts skips it
+    #[serde(default)]
+    pinned: bool,
+}
+
+// How many earlier versions of each script are kept
+const SCRIPT_HISTORY_LIMIT: usize = 10;
+
+// An earlier version of a script, saved in history/<script id>.json when its code was replaced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ScriptRevision {
+    id: String,
+    code: String,
+    name: String,
+    #[serde(default)]
+    version: Option<String>,
+    // When the version was replaced, and by what: "edit", "file", "update" or "rollback"
+    saved_at: u64,
+    reason: String,
+}
+
+// A fetched version of a script waiting for review, see accept_script_update
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingUpdate {
+    code: String,
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    etag: Option<String>,
+    #[serde(default)]
+    last_modified: Option<String>,
+    // Newer content of cached @require files fetched along with it, only put in the cache
+    // once the update is accepted
+    #[serde(default)]
+    dependencies: HashMap<String, ScriptDependency>,
+    fetched_at: u64,
+}
+
+impl UserScript {
+    // Copy header-derived fields, keeping user settings (enabled, order) untouched
+    fn apply_metadata(&mut self, metadata: ScriptMetadata) {
+        if let Some(name) = metadata.name {
+            self.name = name;
+        }
+        self.version = metadata.version;
+        self.description = metadata.description;
+        self.author = metadata.author;
+        self.namespace = metadata.namespace;
+        self.requires = metadata.requires;
+        self.resources = metadata.resources;
+        self.matches = metadata.matches;
+        self.includes = metadata.includes;
+        self.excludes = metadata.excludes;
+        self.exclude_matches = metadata.exclude_matches;
+        self.run_at = metadata.run_at;
+        self.grants = metadata.grants;
+        self.connects = metadata.connects;
+    }
+
+    // Whether gm_xhr may contact this host without asking the user
+    fn connect_allowed(&self, host: &str) -> bool {
+        self.connects.iter()
+            .chain(self.user_connects.iter())
+            .any(|connect| connect_matches(connect, host))
+    }
+
+    // GM functions this script may use. Scripts without any @grant get what their code
+    // references, like Tampermonkey does, so older scripts keep working
+    fn granted_apis(&self) -> Vec<&'static str> {
+        if self.grants.is_empty() {
+            return GM_API_NAMES.iter().copied().filter(|name| self.code.contains(name)).collect();
+        }
+        GM_API_NAMES.iter().copied().filter(|name| self.grants.iter().any(|g| g == name)).collect()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,11 +191,132 @@ struct ScriptDependency {
     url: String,
     code: String,
     last_updated: u64,
+    #[serde(default)]
+    etag: Option<String>,
+    #[serde(default)]
+    last_modified: Option<String>,
+}
+
+// An @resource declaration: `// @resource name url`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ScriptResource {
+    name: String,
+    url: String,
+}
+
+// Downloaded @resource content, cached in resources.json by URL.
+// Resources can be binary (images, fonts) so the content is kept base64 encoded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedResource {
+    url: String,
+    mime_type: String,
+    data: String,
+    last_updated: u64,
+}
+
+// Where GM_download saves files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum DownloadLocation {
+    // The user's Downloads folder
+    #[default]
+    Downloads,
+    // A downloads folder inside the app's data dir
+    DataDir,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct AppSettings {
+    #[serde(default)]
+    download_location: DownloadLocation,
+}
+
+// Last known size, position and state of a window, restored when it's created again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WindowGeometry {
+    // Logical inner size and outer position of the window while it's neither maximized nor fullscreen
+    width: f64,
+    height: f64,
+    x: f64,
+    y: f64,
+    #[serde(default)]
+    maximized: bool,
+    #[serde(default)]
+    fullscreen: bool,
+    // Name of the monitor the window was on
+    #[serde(default)]
+    monitor: Option<String>,
+}
+
+// Label of the main GeoGuessr window
+const MAIN_WINDOW_LABEL: &str = "geoguessr";
+
+// A GeoGuessr window the user can open. Every instance besides the main one has its own
+// browser profile, so it can stay logged into another account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GameInstance {
+    label: String,
+    name: String,
+    // Id of the instance's browser profile under profiles/ in the data dir,
+    // None for the main window which uses the default profile
+    #[serde(default)]
+    profile: Option<String>,
+    // Ids of the enabled scripts this instance runs, None runs all of them
+    #[serde(default)]
+    scripts: Option<Vec<String>>,
+}
+
+impl GameInstance {
+    fn main() -> Self {
+        GameInstance {
+            label: MAIN_WINDOW_LABEL.to_string(),
+            name: "GeoGuessr".to_string(),
+            profile: None,
+            scripts: None,
+        }
+    }
+
+    fn runs_script(&self, script: &UserScript) -> bool {
+        script.enabled && self.scripts.as_ref().map_or(true, |ids| ids.contains(&script.id))
+    }
 }
 
 struct AppState {
     scripts: Mutex<Vec<UserScript>>,
     dependencies: Mutex<HashMap<String, ScriptDependency>>,
+    resources: Mutex<HashMap<String, CachedResource>>,
+    // GM_setValue storage, keyed by script id then value key
+    values: Mutex<HashMap<String, HashMap<String, serde_json::Value>>>,
+    // (script id, host) pairs the user blocked for gm_xhr, only kept for this session
+    denied_connects: Mutex<HashSet<(String, String)>>,
+    // Connect prompts on screen by (script id, host). Requests needing the same approval wait
+    // for that prompt's answer instead of opening their own
+    connect_prompts: Mutex<HashMap<(String, String), tokio::sync::broadcast::Sender<ConnectDecision>>>,
+    // Secret key of each GeoGuessr window's bootstrap script, by window label. A script's API
+    // identifies itself with a token derived from it (see script_token) and native GM commands
+    // only act for the script whose token they get, the page knows every script id
+    window_keys: Mutex<HashMap<String, String>>,
+    // Key of the 'fetch' marker reload_game_window last left for each window, by window label.
+    // get_injection_script only serves a window that sends its marker's key back
+    fetch_keys: Mutex<HashMap<String, String>>,
+    // In-flight gm_xhr transfers by request id, for GM_xmlhttpRequest's abort()
+    // None until the transfer starts, a request aborted before that is just removed
+    xhr_requests: Mutex<HashMap<String, Option<tokio::task::AbortHandle>>>,
+    // GM_registerMenuCommand entries of the pages currently loaded
+    menu_commands: Mutex<Vec<MenuCommand>>,
+    // In-flight GM_download transfers by download id
+    downloads: Mutex<HashMap<String, tokio::task::AbortHandle>>,
+    settings: Mutex<AppSettings>,
+    // GeoGuessr windows the user can open, the main one first
+    instances: Mutex<Vec<GameInstance>>,
+    // Saved window geometry by window label
+    window_geometry: Mutex<HashMap<String, WindowGeometry>>,
+    // When window-state.json was last written, to throttle saves while a window is dragged
+    window_geometry_saved_at: Mutex<Option<std::time::Instant>>,
+    // Files dropped onto a manager window, the only paths add_script_from_file accepts
+    dropped_paths: Mutex<HashSet<PathBuf>>,
+    // Watches the files of scripts linked with link_script_file
+    script_watcher: Mutex<Option<notify_debouncer_mini::Debouncer<notify_debouncer_mini::notify::RecommendedWatcher>>>,
     data_dir: PathBuf,
     discord_client: Mutex<Option<DiscordIpcClient>>,
 }
@@ -60,24 +330,108 @@ impl AppState {
         fs::create_dir_all(&data_dir).ok();
 
         let scripts_file = data_dir.join("scripts.json");
-        let scripts = if scripts_file.exists() {
+        let mut scripts: Vec<UserScript> = if scripts_file.exists() {
             let content = fs::read_to_string(&scripts_file).unwrap_or_default();
             serde_json::from_str(&content).unwrap_or_default()
         } else {
             Vec::new()
         };
 
+        // Re-read headers so scripts saved by older versions pick up newly parsed fields
+        for script in scripts.iter_mut() {
+            script.apply_metadata(parse_metadata(&script.code));
+        }
+
         let dependencies_file = data_dir.join("dependencies.json");
-        let dependencies = if dependencies_file.exists() {
+        let mut dependencies: HashMap<String, ScriptDependency> = if dependencies_file.exists() {
             let content = fs::read_to_string(&dependencies_file).unwrap_or_default();
             serde_json::from_str(&content).unwrap_or_default()
         } else {
             HashMap::new()
         };
 
+        let resources_file = data_dir.join("resources.json");
+        let mut resources: HashMap<String, CachedResource> = if resources_file.exists() {
+            let content = fs::read_to_string(&resources_file).unwrap_or_default();
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            HashMap::new()
+        };
+
+        // Drop cached content that fails the integrity hashes in its URL, e.g. cached before
+        // they were checked. It's fetched again at startup (see resolve_missing_resources)
+        let verified = |url: &str, content: &[u8]| match verify_integrity(url, content) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("[Integrity] Dropping cached {}: {}", url, e);
+                false
+            }
+        };
+        dependencies.retain(|url, dependency| {
+            // Hashes are of the downloaded bytes, which may have started with a BOM
+            !url.contains('#')
+                || verify_integrity(url, format!("\u{feff}{}", dependency.code).as_bytes()).is_ok()
+                || verified(url, dependency.code.as_bytes())
+        });
+        resources.retain(|url, resource| {
+            use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+
+            !url.contains('#') || verified(url, &BASE64.decode(&resource.data).unwrap_or_default())
+        });
+
+        let values_file = data_dir.join("values.json");
+        let values = if values_file.exists() {
+            let content = fs::read_to_string(&values_file).unwrap_or_default();
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            HashMap::new()
+        };
+
+        let settings_file = data_dir.join("settings.json");
+        let settings = if settings_file.exists() {
+            let content = fs::read_to_string(&settings_file).unwrap_or_default();
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            AppSettings::default()
+        };
+
+        let instances_file = data_dir.join("instances.json");
+        let mut instances: Vec<GameInstance> = if instances_file.exists() {
+            let content = fs::read_to_string(&instances_file).unwrap_or_default();
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        if !instances.iter().any(|i| i.label == MAIN_WINDOW_LABEL) {
+            instances.insert(0, GameInstance::main());
+        }
+
+        let window_state_file = data_dir.join("window-state.json");
+        let window_geometry = if window_state_file.exists() {
+            let content = fs::read_to_string(&window_state_file).unwrap_or_default();
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            HashMap::new()
+        };
+
         AppState {
             scripts: Mutex::new(scripts),
             dependencies: Mutex::new(dependencies),
+            resources: Mutex::new(resources),
+            values: Mutex::new(values),
+            denied_connects: Mutex::new(HashSet::new()),
+            connect_prompts: Mutex::new(HashMap::new()),
+            window_keys: Mutex::new(HashMap::new()),
+            fetch_keys: Mutex::new(HashMap::new()),
+            xhr_requests: Mutex::new(HashMap::new()),
+            menu_commands: Mutex::new(Vec::new()),
+            downloads: Mutex::new(HashMap::new()),
+            settings: Mutex::new(settings),
+            instances: Mutex::new(instances),
+            window_geometry: Mutex::new(window_geometry),
+            window_geometry_saved_at: Mutex::new(None),
+            dropped_paths: Mutex::new(HashSet::new()),
+            script_watcher: Mutex::new(None),
             data_dir,
             discord_client: Mutex::new(None),
         }
@@ -100,6 +454,200 @@ impl AppState {
             .map_err(|e| format!("Failed to write dependencies file: {}", e))?;
         Ok(())
     }
+
+    fn save_resources(&self, resources: &HashMap<String, CachedResource>) -> Result<(), String> {
+        let resources_file = self.data_dir.join("resources.json");
+        let content = serde_json::to_string_pretty(resources)
+            .map_err(|e| format!("Failed to serialize resources: {}", e))?;
+        fs::write(&resources_file, content)
+            .map_err(|e| format!("Failed to write resources file: {}", e))?;
+        Ok(())
+    }
+
+    fn save_values(&self, values: &HashMap<String, HashMap<String, serde_json::Value>>) -> Result<(), String> {
+        let values_file = self.data_dir.join("values.json");
+        let content = serde_json::to_string_pretty(values)
+            .map_err(|e| format!("Failed to serialize values: {}", e))?;
+        fs::write(&values_file, content)
+            .map_err(|e| format!("Failed to write values file: {}", e))?;
+        Ok(())
+    }
+
+    fn save_settings(&self, settings: &AppSettings) -> Result<(), String> {
+        let settings_file = self.data_dir.join("settings.json");
+        let content = serde_json::to_string_pretty(settings)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        fs::write(&settings_file, content)
+            .map_err(|e| format!("Failed to write settings file: {}", e))?;
+        Ok(())
+    }
+
+    fn save_instances(&self, instances: &[GameInstance]) -> Result<(), String> {
+        let instances_file = self.data_dir.join("instances.json");
+        let content = serde_json::to_string_pretty(instances)
+            .map_err(|e| format!("Failed to serialize instances: {}", e))?;
+        fs::write(&instances_file, content)
+            .map_err(|e| format!("Failed to write instances file: {}", e))?;
+        Ok(())
+    }
+
+    // The instance a GeoGuessr window belongs to, the main one for unknown labels
+    fn instance(&self, label: &str) -> GameInstance {
+        self.instances.lock().unwrap()
+            .iter()
+            .find(|i| i.label == label)
+            .cloned()
+            .unwrap_or_else(GameInstance::main)
+    }
+
+    fn profile_dir(&self, profile: &str) -> PathBuf {
+        self.data_dir.join("profiles").join(profile)
+    }
+
+    fn save_window_geometry(&self, window_geometry: &HashMap<String, WindowGeometry>) -> Result<(), String> {
+        let window_state_file = self.data_dir.join("window-state.json");
+        let content = serde_json::to_string_pretty(window_geometry)
+            .map_err(|e| format!("Failed to serialize window state: {}", e))?;
+        fs::write(&window_state_file, content)
+            .map_err(|e| format!("Failed to write window state file: {}", e))?;
+        *self.window_geometry_saved_at.lock().unwrap() = Some(std::time::Instant::now());
+        Ok(())
+    }
+
+    // A fresh key for a window whose bootstrap script is being built
+    fn new_window_key(&self, label: &str) -> String {
+        let key = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        self.window_keys.lock().unwrap().insert(label.to_string(), key.clone());
+        key
+    }
+
+    // The script a native GM command from a window was called for
+    fn token_script(&self, window: &tauri::WebviewWindow, token: &str) -> Result<UserScript, String> {
+        let key = self.window_keys.lock().unwrap()
+            .get(window.label())
+            .cloned()
+            .ok_or_else(|| "Unknown script token".to_string())?;
+        self.scripts.lock().unwrap()
+            .iter()
+            .find(|s| script_token(&key, &s.id) == token)
+            .cloned()
+            .ok_or_else(|| "Unknown script token".to_string())
+    }
+
+    fn token_script_id(&self, window: &tauri::WebviewWindow, token: &str) -> Result<String, String> {
+        self.token_script(window, token).map(|script| script.id)
+    }
+
+    // Script ids come from the frontend, only a UUID may become part of the path
+    fn history_file(&self, script_id: &str) -> Result<PathBuf, String> {
+        let id = Uuid::parse_str(script_id).map_err(|_| "Invalid script id".to_string())?;
+        Ok(self.data_dir.join("history").join(format!("{}.json", id)))
+    }
+
+    // Earlier versions of a script, newest first
+    fn load_history(&self, script_id: &str) -> Vec<ScriptRevision> {
+        let Ok(history_file) = self.history_file(script_id) else {
+            return Vec::new();
+        };
+        let content = fs::read_to_string(history_file).unwrap_or_default();
+        serde_json::from_str(&content).unwrap_or_default()
+    }
+
+    fn save_history(&self, script_id: &str, history: &[ScriptRevision]) -> Result<(), String> {
+        let history_file = self.history_file(script_id)?;
+        if let Some(parent) = history_file.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create history folder: {}", e))?;
+        }
+        let content = serde_json::to_string_pretty(history)
+            .map_err(|e| format!("Failed to serialize history: {}", e))?;
+        fs::write(&history_file, content)
+            .map_err(|e| format!("Failed to write history file: {}", e))?;
+        Ok(())
+    }
+
+    // Keep a script's current version in its history before the code is replaced
+    fn record_revision(&self, script: &UserScript, reason: &str) -> Result<(), String> {
+        use chrono::Utc;
+
+        let mut history = self.load_history(&script.id);
+        if history.first().is_some_and(|revision| revision.code == script.code) {
+            return Ok(());
+        }
+        // A linked file is reloaded on every save. Only the version from before the run of saves
+        // is kept, the file's intermediate states would push everything else out
+        if reason == "file" && history.first().is_some_and(|revision| revision.reason == "file") {
+            return Ok(());
+        }
+
+        history.insert(0, ScriptRevision {
+            id: Uuid::new_v4().to_string(),
+            code: script.code.clone(),
+            name: script.name.clone(),
+            version: script.version.clone(),
+            saved_at: Utc::now().timestamp() as u64,
+            reason: reason.to_string(),
+        });
+        history.truncate(SCRIPT_HISTORY_LIMIT);
+        self.save_history(&script.id, &history)
+    }
+
+    // Replace a script's code, re-reading its header and fetching any new @require/@resource.
+    // The replaced code goes into the script's history, `reason` says why it was replaced
+    fn set_script_code(&self, id: &str, code: String, reason: &str) -> Result<UserScript, String> {
+        use chrono::Utc;
+
+        if metadata_header(&code).is_none() {
+            return Err("The script needs a ==UserScript== header".to_string());
+        }
+
+        let mut scripts = self.scripts.lock().unwrap();
+        let mut dependencies = self.dependencies.lock().unwrap();
+        let mut resources = self.resources.lock().unwrap();
+
+        let script = scripts.iter_mut()
+            .find(|s| s.id == id)
+            .ok_or_else(|| "Script not found".to_string())?;
+
+        let metadata = parse_metadata(&code);
+        resolve_dependencies(&metadata, &mut dependencies, &mut resources)?;
+        if script.code != code {
+            self.record_revision(script, reason)?;
+        }
+        script.apply_metadata(metadata);
+        script.code = code;
+        script.last_updated = Some(Utc::now().timestamp() as u64);
+        // The code no longer matches what the server sent, so a 304 must not keep it
+        script.etag = None;
+        script.last_modified = None;
+        // A waiting update was fetched against the replaced code and accepting it would throw
+        // this away. The next check fetches it again
+        script.pending_update = None;
+        let updated = script.clone();
+        collect_unused_dependencies(&scripts, &mut dependencies, &mut resources);
+
+        let scripts_clone = scripts.clone();
+        let dependencies_clone = dependencies.clone();
+        let resources_clone = resources.clone();
+        drop(scripts); // Release lock before saving
+        drop(dependencies);
+        drop(resources);
+        self.save_scripts(&scripts_clone)?;
+        self.save_dependencies(&dependencies_clone)?;
+        self.save_resources(&resources_clone)?;
+
+        Ok(updated)
+    }
+
+    // Folder GM_download saves into, falling back to the data dir when there is no Downloads folder
+    fn download_dir(&self) -> PathBuf {
+        let location = self.settings.lock().unwrap().download_location;
+        match location {
+            DownloadLocation::Downloads => dirs::download_dir()
+                .unwrap_or_else(|| self.data_dir.join("downloads")),
+            DownloadLocation::DataDir => self.data_dir.join("downloads"),
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -108,7 +656,40 @@ struct ScriptMetadata {
     version: Option<String>,
     description: Option<String>,
     author: Option<String>,
+    namespace: Option<String>,
     requires: Vec<String>,
+    resources: Vec<ScriptResource>,
+    matches: Vec<String>,
+    includes: Vec<String>,
+    excludes: Vec<String>,
+    exclude_matches: Vec<String>,
+    run_at: Option<String>,
+    grants: Vec<String>,
+    connects: Vec<String>,
+}
+
+// Collect every value of a metadata key, e.g. all `// @match ...` lines
+fn metadata_values(block: &str, key: &str) -> Vec<String> {
+    use regex::Regex;
+
+    let key_regex = Regex::new(&format!(r"(?m)^\s*//\s*@{}[ \t]+(.+?)\s*$", regex::escape(key))).unwrap();
+    key_regex
+        .captures_iter(block)
+        .filter_map(|caps| caps.get(1).map(|m| m.as_str().to_string()))
+        .collect()
+}
+
+// First value of a single-valued metadata key
+fn metadata_value(block: &str, key: &str) -> Option<String> {
+    metadata_values(block, key).into_iter().next()
+}
+
+// The raw // ==UserScript== ... // ==/UserScript== header, as exposed by GM_info.scriptMetaStr
+fn metadata_header(code: &str) -> Option<String> {
+    use regex::Regex;
+
+    let header_regex = Regex::new(r"(?s)//\s*==UserScript==.*?//\s*==/UserScript==").unwrap();
+    header_regex.find(code).map(|m| m.as_str().to_string())
 }
 
 fn parse_metadata(code: &str) -> ScriptMetadata {
@@ -123,41 +704,261 @@ fn parse_metadata(code: &str) -> ScriptMetadata {
         if let Some(metadata_block) = captures.get(1) {
             let block = metadata_block.as_str();
 
-            // Parse @name
-            if let Some(caps) = Regex::new(r"@name\s+(.+)").unwrap().captures(block) {
-                metadata.name = caps.get(1).map(|m| m.as_str().trim().to_string());
-            }
+            metadata.name = metadata_value(block, "name");
+            metadata.version = metadata_value(block, "version");
+            metadata.description = metadata_value(block, "description");
+            metadata.author = metadata_value(block, "author");
+            metadata.namespace = metadata_value(block, "namespace");
+
+            // @require can appear multiple times, only remote URLs are supported
+            metadata.requires = metadata_values(block, "require")
+                .into_iter()
+                .filter(|url| url.starts_with("https://") || url.starts_with("http://"))
+                .collect();
+
+            // @resource name url, only remote URLs are supported
+            metadata.resources = metadata_values(block, "resource")
+                .into_iter()
+                .filter_map(|value| {
+                    let mut parts = value.split_whitespace();
+                    let name = parts.next()?.to_string();
+                    let url = parts.next()?.to_string();
+                    (url.starts_with("https://") || url.starts_with("http://")).then_some(ScriptResource { name, url })
+                })
+                .collect();
 
-            // Parse @version
-            if let Some(caps) = Regex::new(r"@version\s+(.+)").unwrap().captures(block) {
-                metadata.version = caps.get(1).map(|m| m.as_str().trim().to_string());
-            }
+            // URL rules deciding which pages the script runs on
+            metadata.matches = metadata_values(block, "match");
+            metadata.includes = metadata_values(block, "include");
+            metadata.excludes = metadata_values(block, "exclude");
+            metadata.exclude_matches = metadata_values(block, "exclude-match");
 
-            // Parse @description
-            if let Some(caps) = Regex::new(r"@description\s+(.+)").unwrap().captures(block) {
-                metadata.description = caps.get(1).map(|m| m.as_str().trim().to_string());
-            }
+            // document-start, document-body, document-end or document-idle (default)
+            metadata.run_at = metadata_value(block, "run-at");
+
+            // GM functions the script asks for, "none" grants nothing
+            metadata.grants = metadata_values(block, "grant");
+
+            // Hosts GM_xmlhttpRequest may contact
+            metadata.connects = metadata_values(block, "connect");
+        }
+    }
+
+    metadata
+}
+
+// Convert an @match pattern (scheme://host/path) into a JS regex source
+fn match_pattern_to_regex(pattern: &str) -> Option<String> {
+    use regex::Regex;
+
+    if pattern == "<all_urls>" {
+        return Some("^(https?|wss?|file|ftp)://.*$".to_string());
+    }
+
+    let pattern_regex = Regex::new(r"^(\*|[a-z][a-z0-9+.-]*)://([^/]*)(/.*)?$").unwrap();
+    let caps = pattern_regex.captures(pattern)?;
 
-            // Parse @author
-            if let Some(caps) = Regex::new(r"@author\s+(.+)").unwrap().captures(block) {
-                metadata.author = caps.get(1).map(|m| m.as_str().trim().to_string());
+    let scheme = match &caps[1] {
+        "*" => "https?".to_string(),
+        scheme => escape_js_regex(scheme),
+    };
+
+    let host = &caps[2];
+    let host = if host == "*" {
+        "[^/]*".to_string()
+    } else if let Some(domain) = host.strip_prefix("*.") {
+        // *.example.com matches example.com and any of its subdomains
+        format!("([^/]*\\.)?{}", glob_to_regex(domain, "[^/]*"))
+    } else {
+        glob_to_regex(host, "[^/]*")
+    };
+
+    let path = caps.get(3).map(|m| m.as_str()).unwrap_or("/*");
+
+    Some(format!("^{}://{}{}$", scheme, host, glob_to_regex(path, ".*")))
+}
+
+// Convert an @include/@exclude glob (or /regex/) into a JS regex source
+fn include_pattern_to_regex(pattern: &str) -> String {
+    if pattern.len() > 2 && pattern.starts_with('/') && pattern.ends_with('/') {
+        return pattern[1..pattern.len() - 1].to_string();
+    }
+
+    // Greasemonkey's magic .tld matches any top level domain
+    let regex = glob_to_regex(pattern, ".*").replace("\\.tld", "\\.[a-z]{2,}(\\.[a-z]{2,})?");
+    format!("^{}$", regex)
+}
+
+fn glob_to_regex(glob: &str, wildcard: &str) -> String {
+    glob.split('*')
+        .map(escape_js_regex)
+        .collect::<Vec<_>>()
+        .join(wildcard)
+}
+
+fn escape_js_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\^$.|?*+()[]{}/".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Check a host against one @connect value (domain, subdomain wildcard, IP, "self" or "*")
+fn connect_matches(connect: &str, host: &str) -> bool {
+    let connect = connect.trim().trim_start_matches("*.").to_lowercase();
+    let host = host.to_lowercase();
+    match connect.as_str() {
+        // "*" is for public hosts only, local network access has to be declared explicitly
+        "*" => !is_local_host(&host),
+        "self" => host == "geoguessr.com" || host.ends_with(".geoguessr.com"),
+        _ => host == connect || host.ends_with(&format!(".{}", connect)),
+    }
+}
+
+fn is_local_host(host: &str) -> bool {
+    if host == "localhost" || host.ends_with(".localhost") || host.ends_with(".local") {
+        return true;
+    }
+    host.trim_start_matches('[').trim_end_matches(']')
+        .parse::<std::net::IpAddr>()
+        .map_or(false, is_local_ip)
+}
+
+fn is_local_ip(ip: std::net::IpAddr) -> bool {
+    use std::net::IpAddr;
+
+    match ip {
+        IpAddr::V4(ip) => ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified(),
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_local_ip(IpAddr::V4(ip));
             }
+            let first = ip.segments()[0];
+            // Loopback, unique local (fc00::/7) and link-local (fe80::/10)
+            ip.is_loopback() || ip.is_unspecified() || (first & 0xfe00) == 0xfc00 || (first & 0xffc0) == 0xfe80
+        }
+    }
+}
 
-            // Parse @require (can appear multiple times)
-            let require_regex = Regex::new(r"@require\s+(https?://\S+)").unwrap();
-            for caps in require_regex.captures_iter(block) {
-                if let Some(url) = caps.get(1) {
-                    metadata.requires.push(url.as_str().trim().to_string());
+// Resolver for GM_xmlhttpRequest. A host name that isn't local itself only gets its public
+// addresses, so an allowed host can't be pointed at the local network by DNS (rebinding).
+// Local network hosts have to be declared by IP, localhost or a .local name
+struct PublicHostResolver;
+
+impl reqwest::dns::Resolve for PublicHostResolver {
+    fn resolve(&self, name: hyper::client::connect::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let lookup_host = host.clone();
+            let mut addrs = tokio::task::spawn_blocking(move || {
+                use std::net::ToSocketAddrs;
+                (lookup_host.as_str(), 0).to_socket_addrs().map(|addrs| addrs.collect::<Vec<_>>())
+            })
+            .await??;
+            if !is_local_host(&host) {
+                addrs.retain(|addr| !is_local_ip(addr.ip()));
+                if addrs.is_empty() {
+                    return Err(format!("{} resolves to a local network address", host).into());
                 }
             }
-        }
+            let addrs: reqwest::dns::Addrs = Box::new(addrs.into_iter());
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(addrs)
+        })
     }
+}
 
-    metadata
+// GM_info for one script, shaped like Tampermonkey's
+fn script_info(script: &UserScript) -> serde_json::Value {
+    let header = metadata_header(&script.code).unwrap_or_default();
+    serde_json::json!({
+        "script": {
+            "name": script.name,
+            "namespace": script.namespace,
+            "description": script.description,
+            "version": script.version,
+            "author": script.author,
+            "matches": script.matches,
+            "includes": script.includes,
+            "excludes": script.excludes,
+            "exclude-match": script.exclude_matches,
+            "requires": script.requires,
+            "resources": script.resources,
+            "grant": script.grants,
+            "connects": script.connects,
+            "run-at": script.run_at.as_deref().unwrap_or("document-idle"),
+            "uuid": script.id,
+            "header": header,
+        },
+        "scriptMetaStr": header,
+        "scriptHandler": "GeoGuessr Desktop",
+        "version": env!("CARGO_PKG_VERSION"),
+    })
+}
+
+// Names of the @resource files a script reads with GM_getResourceText/GM_getResourceURL.
+// The page loads their content with gm_get_resource when the script starts
+fn script_resource_names(script: &UserScript) -> Vec<String> {
+    if !reads_resources(script) {
+        return Vec::new();
+    }
+    script.resources.iter().map(|resource| resource.name.clone()).collect()
+}
+
+fn reads_resources(script: &UserScript) -> bool {
+    script.granted_apis().iter().any(|name| {
+        matches!(*name, "GM_getResourceText" | "GM_getResourceURL" | "GM.getResourceText" | "GM.getResourceUrl")
+    })
+}
+
+// URL rules for the injected loader, as JS regex sources
+fn script_url_rules(script: &UserScript) -> serde_json::Value {
+    let include: Vec<String> = script.matches.iter()
+        .filter_map(|p| match_pattern_to_regex(p))
+        .chain(script.includes.iter().map(|p| include_pattern_to_regex(p)))
+        .collect();
+
+    // Declared rules that are all invalid must not widen the script to every page
+    let include = if include.is_empty() && !(script.matches.is_empty() && script.includes.is_empty()) {
+        vec!["(?!)".to_string()]
+    } else {
+        include
+    };
+
+    let exclude: Vec<String> = script.exclude_matches.iter()
+        .filter_map(|p| match_pattern_to_regex(p))
+        .chain(script.excludes.iter().map(|p| include_pattern_to_regex(p)))
+        .collect();
+
+    serde_json::json!({ "include": include, "exclude": exclude })
 }
 
-fn fetch_script_from_url(url: &str) -> Result<String, String> {
+// A downloaded script or @require with the validators to ask the server later whether it changed
+struct FetchedScript {
+    code: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+fn fetch_script_from_url(url: &str) -> Result<FetchedScript, String> {
+    fetch_script_if_modified(url, None, None)?
+        .ok_or_else(|| format!("Unexpected 304 Not Modified from {}", url))
+}
+
+// Fetch a script, sending If-None-Match/If-Modified-Since for the copy we have.
+// Ok(None) means the server answered 304 Not Modified and that copy is current.
+// Integrity hashes in the URL's fragment are checked against the body as downloaded
+fn fetch_script_if_modified(
+    url: &str,
+    etag: Option<&str>,
+    last_modified: Option<&str>,
+) -> Result<Option<FetchedScript>, String> {
     use reqwest::blocking::Client;
+    use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+    use reqwest::StatusCode;
     use std::time::Duration;
 
     // Validate URL starts with https
@@ -172,9 +973,17 @@ fn fetch_script_from_url(url: &str) -> Result<String, String> {
         .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
 
     // Fetch the script
-    let response = client
-        .get(url)
-        .header("User-Agent", "GeoGuessrDesktop/1.0")
+    let mut request = client
+        .get(without_fragment(url))
+        .header("User-Agent", "GeoGuessrDesktop/1.0");
+    if let Some(etag) = etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let response = request
         .send()
         .map_err(|e| {
             if e.is_timeout() {
@@ -186,6 +995,11 @@ fn fetch_script_from_url(url: &str) -> Result<String, String> {
             }
         })?;
 
+    // Only a conditional request can be answered with "unchanged"
+    if response.status() == StatusCode::NOT_MODIFIED && (etag.is_some() || last_modified.is_some()) {
+        return Ok(None);
+    }
+
     // Check status code
     if !response.status().is_success() {
         return Err(format!("HTTP {}: {}", response.status().as_u16(), response.status().canonical_reason().unwrap_or("Unknown error")));
@@ -199,41 +1013,258 @@ fn fetch_script_from_url(url: &str) -> Result<String, String> {
         }
     }
 
-    // Get response body
-    let body = response.text().map_err(|e| format!("Failed to read response: {}", e))?;
+    let header = |name: reqwest::header::HeaderName| response.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+
+    // Get response body. Hashes are of the raw bytes, so check them before decoding
+    let bytes = response.bytes().map_err(|e| format!("Failed to read response: {}", e))?;
 
     // Check size (10MB limit)
-    if body.len() > 10 * 1024 * 1024 {
+    if bytes.len() > 10 * 1024 * 1024 {
         return Err("Script too large (>10MB)".to_string());
     }
+    verify_integrity(url, &bytes)?;
 
-    Ok(body)
+    let body = String::from_utf8_lossy(&bytes);
+    let body = body.strip_prefix('\u{feff}').unwrap_or(&body).to_string();
+
+    Ok(Some(FetchedScript { code: body, etag, last_modified }))
 }
 
-fn fetch_script_with_dependencies(
-    url: &str,
-    dependency_cache: &mut HashMap<String, ScriptDependency>
-) -> Result<UserScript, String> {
+// Integrity hashes in the fragment of a @require/@resource URL, as (algorithm, hash as written,
+// decoded digest). Like Tampermonkey this takes `#sha256=<hash>` as well as `#sha256-<hash>`,
+// several separated by commas, with hex or base64 hashes. Algorithms other than SHA-256/384/512
+// are ignored, a hash that can't be decoded gets an empty digest so it never matches
+fn integrity_hashes(url: &str) -> Vec<(&'static str, String, Vec<u8>)> {
+    use base64::{Engine as _, engine::general_purpose::{STANDARD as BASE64, URL_SAFE as BASE64_URL_SAFE}};
+
+    let Some((_, fragment)) = url.split_once('#') else {
+        return Vec::new();
+    };
+
+    fragment.split([',', ';'])
+        .filter_map(|entry| {
+            let (algorithm, hash) = entry.trim().split_once(['=', '-'])?;
+            let (algorithm, length) = match algorithm.to_ascii_lowercase().as_str() {
+                "sha256" => ("sha256", 32),
+                "sha384" => ("sha384", 48),
+                "sha512" => ("sha512", 64),
+                _ => return None,
+            };
+
+            let hex = (hash.len() == length * 2 && hash.bytes().all(|b| b.is_ascii_hexdigit()))
+                .then(|| (0..length).map(|i| u8::from_str_radix(&hash[i * 2..i * 2 + 2], 16).unwrap()).collect());
+            let digest = hex
+                .or_else(|| BASE64.decode(hash).ok())
+                .or_else(|| BASE64_URL_SAFE.decode(hash).ok())
+                .unwrap_or_default();
+
+            Some((algorithm, hash.to_string(), digest))
+        })
+        .collect()
+}
+
+// Check downloaded @require/@resource content against the integrity hashes in its URL. As with
+// SRI, only the strongest algorithm given counts and matching any one of its hashes is enough
+fn verify_integrity(url: &str, content: &[u8]) -> Result<(), String> {
+    use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+    use sha2::{Digest, Sha256, Sha384, Sha512};
+
+    let hashes = integrity_hashes(url);
+    let strength = |algorithm: &str| ["sha256", "sha384", "sha512"].iter().position(|a| *a == algorithm);
+    let Some(algorithm) = hashes.iter().map(|(algorithm, _, _)| *algorithm).max_by_key(|a| strength(a)) else {
+        return Ok(());
+    };
+
+    let actual = match algorithm {
+        "sha256" => Sha256::digest(content).to_vec(),
+        "sha384" => Sha384::digest(content).to_vec(),
+        _ => Sha512::digest(content).to_vec(),
+    };
+    let expected: Vec<&str> = hashes.iter()
+        .filter(|(a, _, _)| *a == algorithm)
+        .map(|(_, hash, _)| hash.as_str())
+        .collect();
+    if hashes.iter().any(|(a, _, digest)| *a == algorithm && *digest == actual) {
+        return Ok(());
+    }
+    Err(format!(
+        "Integrity check failed: expected {} {}, got {}",
+        algorithm, expected.join(" or "), BASE64.encode(&actual)
+    ))
+}
+
+// The URL to download, without the fragment that may carry integrity hashes
+fn without_fragment(url: &str) -> &str {
+    url.split('#').next().unwrap_or(url)
+}
+
+// Fetch an @require into a cache entry, refusing content that fails its integrity hashes
+fn fetch_dependency(url: &str) -> Result<ScriptDependency, String> {
+    use chrono::Utc;
+
+    let fetched = fetch_script_from_url(url)?;
+    Ok(ScriptDependency {
+        url: url.to_string(),
+        code: fetched.code,
+        last_updated: Utc::now().timestamp() as u64,
+        etag: fetched.etag,
+        last_modified: fetched.last_modified,
+    })
+}
+
+// Fetch an @resource, which unlike scripts may be any content type
+fn fetch_resource_from_url(url: &str) -> Result<CachedResource, String> {
+    use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
     use chrono::Utc;
+    use reqwest::blocking::Client;
+    use std::time::Duration;
+
+    if !url.starts_with("https://") {
+        return Err("Only HTTPS URLs are supported for security reasons".to_string());
+    }
+
+    let client = Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let response = client
+        .get(without_fragment(url))
+        .header("User-Agent", "GeoGuessrDesktop/1.0")
+        .send()
+        .map_err(|e| format!("Network error: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}: {}", response.status().as_u16(), response.status().canonical_reason().unwrap_or("Unknown error")));
+    }
+
+    let mime_type = response.headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(';').next().unwrap_or("").trim().to_string())
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+
+    let bytes = response.bytes().map_err(|e| format!("Failed to read response: {}", e))?;
 
+    // Check size (10MB limit)
+    if bytes.len() > 10 * 1024 * 1024 {
+        return Err("Resource too large (>10MB)".to_string());
+    }
+    verify_integrity(url, &bytes)?;
+
+    Ok(CachedResource {
+        url: url.to_string(),
+        mime_type,
+        data: BASE64.encode(&bytes),
+        last_updated: Utc::now().timestamp() as u64,
+    })
+}
+
+fn fetch_script_with_dependencies(
+    url: &str,
+    dependency_cache: &mut HashMap<String, ScriptDependency>,
+    resource_cache: &mut HashMap<String, CachedResource>
+) -> Result<UserScript, String> {
     // Fetch main script
-    let code = fetch_script_from_url(url)?;
+    let fetched = fetch_script_from_url(url)?;
+
+    let mut script = script_with_dependencies(fetched.code, Some(url.to_string()), dependency_cache, resource_cache)?;
+    script.etag = fetched.etag;
+    script.last_modified = fetched.last_modified;
+    Ok(script)
+}
+
+// Fetch a newer copy of a script from its URL with a conditional request, along with newer
+// content of its cached @require files. Ok(None) when the server says the code we have is
+// still current and none of those changed
+fn fetch_script_update(
+    script: &UserScript,
+    dependency_cache: &mut HashMap<String, ScriptDependency>,
+    resource_cache: &mut HashMap<String, CachedResource>
+) -> Result<Option<(UserScript, HashMap<String, ScriptDependency>)>, String> {
+    let url = script.url.as_ref()
+        .ok_or_else(|| "Cannot refresh manually added script".to_string())?;
+
+    let Some(fetched) = fetch_script_if_modified(url, script.etag.as_deref(), script.last_modified.as_deref())? else {
+        let refreshed = refresh_dependencies(&script.requires, dependency_cache);
+        return Ok((!refreshed.is_empty()).then(|| (script.clone(), refreshed)));
+    };
+
+    let refreshed = refresh_dependencies(&parse_metadata(&fetched.code).requires, dependency_cache);
+    let mut updated = script_with_dependencies(fetched.code, Some(url.clone()), dependency_cache, resource_cache)?;
+    updated.etag = fetched.etag;
+    updated.last_modified = fetched.last_modified;
+    Ok(Some((updated, refreshed)))
+}
+
+// Build a UserScript from its code, fetching any @require and @resource not cached yet
+fn script_with_dependencies(
+    code: String,
+    url: Option<String>,
+    dependency_cache: &mut HashMap<String, ScriptDependency>,
+    resource_cache: &mut HashMap<String, CachedResource>
+) -> Result<UserScript, String> {
+    use chrono::Utc;
 
     // Parse metadata
     let metadata = parse_metadata(&code);
+    resolve_dependencies(&metadata, dependency_cache, resource_cache)?;
+
+    // Create UserScript
+    let mut script = UserScript {
+        id: Uuid::new_v4().to_string(),
+        name: "Unnamed Script".to_string(),
+        code,
+        enabled: true,
+        order: 0, // Will be set by the caller
+        url,
+        version: None,
+        description: None,
+        author: None,
+        namespace: None,
+        requires: Vec::new(),
+        resources: Vec::new(),
+        matches: Vec::new(),
+        includes: Vec::new(),
+        excludes: Vec::new(),
+        exclude_matches: Vec::new(),
+        run_at: None,
+        grants: Vec::new(),
+        connects: Vec::new(),
+        local_path: None,
+        user_connects: Vec::new(),
+        last_updated: Some(Utc::now().timestamp() as u64),
+        last_fetch_error: None,
+        etag: None,
+        last_modified: None,
+        pending_update: None,
+        rejected_update: None,
+        pinned: false,
+    };
+    script.apply_metadata(metadata);
 
+    Ok(script)
+}
+
+// Fetch the @require and @resource files of a script that aren't cached yet
+fn resolve_dependencies(
+    metadata: &ScriptMetadata,
+    dependency_cache: &mut HashMap<String, ScriptDependency>,
+    resource_cache: &mut HashMap<String, CachedResource>
+) -> Result<(), String> {
     // Fetch dependencies
     for dep_url in &metadata.requires {
         // Check if already in cache
         if !dependency_cache.contains_key(dep_url) {
             // Fetch dependency
-            match fetch_script_from_url(dep_url) {
-                Ok(dep_code) => {
-                    let dependency = ScriptDependency {
-                        url: dep_url.clone(),
-                        code: dep_code,
-                        last_updated: Utc::now().timestamp() as u64,
-                    };
+            match fetch_dependency(dep_url) {
+                Ok(dependency) => {
                     dependency_cache.insert(dep_url.clone(), dependency);
                 }
                 Err(e) => {
@@ -243,23 +1274,151 @@ fn fetch_script_with_dependencies(
         }
     }
 
-    // Create UserScript
-    let script = UserScript {
-        id: Uuid::new_v4().to_string(),
-        name: metadata.name.unwrap_or_else(|| "Unnamed Script".to_string()),
-        code,
-        enabled: true,
-        order: 0, // Will be set by add_script_from_url
-        url: Some(url.to_string()),
-        version: metadata.version,
-        description: metadata.description,
-        author: metadata.author,
-        requires: metadata.requires,
-        last_updated: Some(Utc::now().timestamp() as u64),
-        last_fetch_error: None,
+    // Fetch resources
+    for resource in &metadata.resources {
+        if !resource_cache.contains_key(&resource.url) {
+            let cached = fetch_resource_from_url(&resource.url)
+                .map_err(|e| format!("Failed to fetch resource {} ({}): {}", resource.name, resource.url, e))?;
+            resource_cache.insert(resource.url.clone(), cached);
+        }
+    }
+
+    Ok(())
+}
+
+// Whether a @require URL pins its version with a `v` query parameter (`lib.js?v=3`).
+// Its content isn't expected to change, a new version comes with a new URL
+fn is_versioned_url(url: &str) -> bool {
+    reqwest::Url::parse(url)
+        .map(|url| url.query_pairs().any(|(key, value)| key == "v" && !value.is_empty()))
+        .unwrap_or(false)
+}
+
+// Re-check cached @require files with conditional requests, returning the ones whose content
+// changed. The cache itself is left alone, missing ones are left to resolve_dependencies
+fn refresh_dependencies(
+    urls: &[String],
+    dependency_cache: &HashMap<String, ScriptDependency>
+) -> HashMap<String, ScriptDependency> {
+    use chrono::Utc;
+
+    let mut refreshed = HashMap::new();
+    for url in urls {
+        // Content pinned by an integrity hash can't change without failing it
+        if is_versioned_url(url) || !integrity_hashes(url).is_empty() {
+            continue;
+        }
+        let Some(cached) = dependency_cache.get(url) else {
+            continue;
+        };
+
+        match fetch_script_if_modified(url, cached.etag.as_deref(), cached.last_modified.as_deref()) {
+            Ok(Some(fetched)) if fetched.code != cached.code => {
+                refreshed.insert(url.clone(), ScriptDependency {
+                    url: url.clone(),
+                    code: fetched.code,
+                    last_updated: Utc::now().timestamp() as u64,
+                    etag: fetched.etag,
+                    last_modified: fetched.last_modified,
+                });
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("[Dependencies] Failed to refresh {}: {}", url, e),
+        }
+    }
+    refreshed
+}
+
+// Scripts using each @require and @resource URL, counting the ones of updates waiting for review
+fn dependency_users(scripts: &[UserScript]) -> HashMap<String, Vec<&UserScript>> {
+    let mut users: HashMap<String, Vec<&UserScript>> = HashMap::new();
+    for script in scripts {
+        let pending = script.pending_update.as_ref().map(|update| parse_metadata(&update.code));
+        let urls = script.requires.iter()
+            .chain(script.resources.iter().map(|r| &r.url))
+            .chain(pending.iter().flat_map(|m| m.requires.iter().chain(m.resources.iter().map(|r| &r.url))));
+        for url in urls {
+            let entry = users.entry(url.clone()).or_default();
+            if !entry.iter().any(|s| s.id == script.id) {
+                entry.push(script);
+            }
+        }
+    }
+    users
+}
+
+// Drop cached @require and @resource files no script uses anymore. GEF stays, the loader
+// always needs it. Returns whether anything was removed
+fn collect_unused_dependencies(
+    scripts: &[UserScript],
+    dependency_cache: &mut HashMap<String, ScriptDependency>,
+    resource_cache: &mut HashMap<String, CachedResource>
+) -> bool {
+    let users = dependency_users(scripts);
+    let cached = dependency_cache.len() + resource_cache.len();
+
+    dependency_cache.retain(|url, _| url == GEF_URL || users.contains_key(url.as_str()));
+    resource_cache.retain(|url, _| users.contains_key(url.as_str()));
+
+    dependency_cache.len() + resource_cache.len() != cached
+}
+
+// A cached @require or @resource file as listed by get_dependencies
+#[derive(Debug, Clone, Serialize)]
+struct DependencyInfo {
+    url: String,
+    // "require" or "resource"
+    kind: String,
+    // Size of the content in bytes
+    size: usize,
+    last_updated: u64,
+    // Seconds since the content was downloaded
+    age: u64,
+    // Names of the scripts using it
+    scripts: Vec<String>,
+}
+
+#[tauri::command]
+fn get_dependencies(state: tauri::State<AppState>) -> Result<Vec<DependencyInfo>, String> {
+    use chrono::Utc;
+
+    let scripts = state.scripts.lock().unwrap();
+    let dependencies = state.dependencies.lock().unwrap();
+    let resources = state.resources.lock().unwrap();
+
+    let now = Utc::now().timestamp() as u64;
+    let users = dependency_users(&scripts);
+    let script_names = |url: &str| -> Vec<String> {
+        users.get(url)
+            .map(|scripts| scripts.iter().map(|s| s.name.clone()).collect())
+            .unwrap_or_default()
     };
 
-    Ok(script)
+    let mut listed: Vec<DependencyInfo> = dependencies.values()
+        .map(|dependency| DependencyInfo {
+            url: dependency.url.clone(),
+            kind: "require".to_string(),
+            size: dependency.code.len(),
+            last_updated: dependency.last_updated,
+            age: now.saturating_sub(dependency.last_updated),
+            scripts: script_names(&dependency.url),
+        })
+        .chain(resources.values().map(|resource| {
+            // Size of the decoded content rather than of its base64 text
+            let padding = resource.data.bytes().rev().take_while(|&b| b == b'=').count();
+            DependencyInfo {
+                url: resource.url.clone(),
+                kind: "resource".to_string(),
+                size: (resource.data.len() / 4 * 3).saturating_sub(padding),
+                last_updated: resource.last_updated,
+                age: now.saturating_sub(resource.last_updated),
+                scripts: script_names(&resource.url),
+            }
+        }))
+        .collect();
+    listed.sort_by(|a, b| a.url.cmp(&b.url));
+
+    Ok(listed)
 }
 
 #[tauri::command]
@@ -272,6 +1431,7 @@ fn get_scripts(state: tauri::State<AppState>) -> Result<Vec<UserScript>, String>
 fn add_script_from_url(url: String, state: tauri::State<AppState>) -> Result<UserScript, String> {
     let mut scripts = state.scripts.lock().unwrap();
     let mut dependencies = state.dependencies.lock().unwrap();
+    let mut resources = state.resources.lock().unwrap();
 
     // Check for duplicate URLs
     if scripts.iter().any(|s| s.url.as_ref() == Some(&url)) {
@@ -279,7 +1439,7 @@ fn add_script_from_url(url: String, state: tauri::State<AppState>) -> Result<Use
     }
 
     // Fetch script with dependencies
-    let mut new_script = fetch_script_with_dependencies(&url, &mut dependencies)?;
+    let mut new_script = fetch_script_with_dependencies(&url, &mut dependencies, &mut resources)?;
 
     // Assign order (highest + 1)
     let max_order = scripts.iter().map(|s| s.order).max().unwrap_or(-1);
@@ -289,165 +1449,1005 @@ fn add_script_from_url(url: String, state: tauri::State<AppState>) -> Result<Use
     scripts.push(new_script.clone());
     let scripts_clone = scripts.clone();
     let dependencies_clone = dependencies.clone();
+    let resources_clone = resources.clone();
     drop(scripts); // Release lock before saving
     drop(dependencies);
+    drop(resources);
     state.save_scripts(&scripts_clone)?;
     state.save_dependencies(&dependencies_clone)?;
+    state.save_resources(&resources_clone)?;
 
     Ok(new_script)
 }
 
+// Import a .user.js from disk, e.g. private scripts that aren't hosted anywhere
 #[tauri::command]
-fn reorder_script(id: String, new_order: i32, state: tauri::State<AppState>) -> Result<(), String> {
-    let mut scripts = state.scripts.lock().unwrap();
-
-    if let Some(script) = scripts.iter_mut().find(|s| s.id == id) {
-        script.order = new_order;
-        state.save_scripts(&scripts)?;
-        Ok(())
-    } else {
-        Err("Script not found".to_string())
+fn add_script_from_file(path: String, window: tauri::WebviewWindow, state: tauri::State<AppState>) -> Result<UserScript, String> {
+    let path = PathBuf::from(path);
+    if !MANAGER_WINDOW_LABELS.contains(&window.label()) || !state.dropped_paths.lock().unwrap().remove(&path) {
+        return Err(format!("{} wasn't dropped onto the manager", path.display()));
     }
+    import_script_file(&state, &path)
 }
 
-#[tauri::command]
-fn toggle_script(id: String, enabled: bool, state: tauri::State<AppState>) -> Result<(), String> {
-    let mut scripts = state.scripts.lock().unwrap();
-
-    if let Some(script) = scripts.iter_mut().find(|s| s.id == id) {
-        script.enabled = enabled;
-        state.save_scripts(&scripts)?;
-        Ok(())
-    } else {
-        Err("Script not found".to_string())
+// Add a userscript from a file the user handed over, see add_script_from_file and import_dropped_files
+fn import_script_file(state: &AppState, path: &std::path::Path) -> Result<UserScript, String> {
+    let code = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if metadata_header(&code).is_none() {
+        return Err(format!("{} is not a userscript (no ==UserScript== header)", path.display()));
     }
-}
-
-#[tauri::command]
-fn delete_script(id: String, state: tauri::State<AppState>) -> Result<(), String> {
-    let mut scripts = state.scripts.lock().unwrap();
-    scripts.retain(|s| s.id != id);
-    state.save_scripts(&scripts)?;
-    Ok(())
-}
-
-#[tauri::command]
-fn refresh_script(id: String, state: tauri::State<AppState>) -> Result<UserScript, String> {
-    use chrono::Utc;
 
     let mut scripts = state.scripts.lock().unwrap();
     let mut dependencies = state.dependencies.lock().unwrap();
+    let mut resources = state.resources.lock().unwrap();
 
-    // Find script
-    let script_index = scripts.iter().position(|s| s.id == id)
-        .ok_or_else(|| "Script not found".to_string())?;
-
-    let script = &scripts[script_index];
-
-    // Check if script has URL
-    let url = script.url.as_ref()
-        .ok_or_else(|| "Cannot refresh manually added script".to_string())?;
-
-    // Preserve user settings
-    let preserved_enabled = script.enabled;
-    let preserved_order = script.order;
-    let preserved_id = script.id.clone();
-
-    // Fetch fresh copy
-    let mut updated_script = fetch_script_with_dependencies(url, &mut dependencies)?;
+    let mut new_script = script_with_dependencies(code, None, &mut dependencies, &mut resources)?;
 
-    // Restore user settings
-    updated_script.id = preserved_id;
-    updated_script.enabled = preserved_enabled;
-    updated_script.order = preserved_order;
-    updated_script.last_updated = Some(Utc::now().timestamp() as u64);
-    updated_script.last_fetch_error = None;
-
-    // Update in list
-    scripts[script_index] = updated_script.clone();
+    // Assign order (highest + 1)
+    let max_order = scripts.iter().map(|s| s.order).max().unwrap_or(-1);
+    new_script.order = max_order + 1;
 
+    // Save
+    scripts.push(new_script.clone());
     let scripts_clone = scripts.clone();
     let dependencies_clone = dependencies.clone();
-    drop(scripts);
+    let resources_clone = resources.clone();
+    drop(scripts); // Release lock before saving
     drop(dependencies);
+    drop(resources);
     state.save_scripts(&scripts_clone)?;
     state.save_dependencies(&dependencies_clone)?;
+    state.save_resources(&resources_clone)?;
 
-    Ok(updated_script)
+    Ok(new_script)
+}
+
+// Outcome of importing a file dropped onto a GeoGuessr window, sent to that window
+#[derive(Debug, Clone, Serialize)]
+struct ScriptImport {
+    path: String,
+    script: Option<UserScript>,
+    error: Option<String>,
+}
+
+// Files dropped onto a manager window are remembered for its add_script_from_file calls.
+// GeoGuessr windows get no say in which files are read, .js files dropped onto them are
+// imported here and the result is sent to the page's settings panel
+fn handle_dropped_files(window: &tauri::Window, paths: &[PathBuf]) {
+    let app = window.app_handle().clone();
+    let state = app.state::<AppState>();
+    if MANAGER_WINDOW_LABELS.contains(&window.label()) {
+        state.dropped_paths.lock().unwrap().extend(paths.iter().cloned());
+        return;
+    }
+    if !state.instances.lock().unwrap().iter().any(|i| i.label == window.label()) {
+        return;
+    }
+
+    let label = window.label().to_string();
+    let paths: Vec<PathBuf> = paths.iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("js")))
+        .cloned()
+        .collect();
+    // Importing fetches @require/@resource, keep that off the event loop
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        for path in paths {
+            let result = import_script_file(&state, &path);
+            let _ = app.emit_to(label.as_str(), "script_import", ScriptImport {
+                path: path.to_string_lossy().to_string(),
+                error: result.as_ref().err().cloned(),
+                script: result.ok(),
+            });
+        }
+    });
+}
+
+// Starting point for scripts written in the editor
+fn new_script_template(name: &str) -> String {
+    format!(
+        r#"// ==UserScript==
+// @name         {}
+// @namespace    geoguessr-desktop
+// @version      0.1.0
+// @match        https://www.geoguessr.com/*
+// @grant        none
+// ==/UserScript==
+
+(function() {{
+    'use strict';
+
+}})();
+"#,
+        name
+    )
 }
 
 #[tauri::command]
-fn auto_update_scripts(state: tauri::State<AppState>) -> Result<usize, String> {
-    use chrono::Utc;
+fn create_script(window: tauri::WebviewWindow, state: tauri::State<AppState>) -> Result<UserScript, String> {
+    if !MANAGER_WINDOW_LABELS.contains(&window.label()) {
+        return Err("Scripts can only be created from the manager".to_string());
+    }
 
     let mut scripts = state.scripts.lock().unwrap();
     let mut dependencies = state.dependencies.lock().unwrap();
+    let mut resources = state.resources.lock().unwrap();
 
-    let now = Utc::now().timestamp() as u64;
-    let one_day = 24 * 60 * 60;
-    let one_hour = 60 * 60;
-    let mut updated_count = 0;
-
-    for script in scripts.iter_mut() {
-        // Only update scripts with URLs
-        if let Some(url) = &script.url {
-            // Skip if updated recently (< 24 hours)
-            if let Some(last_updated) = script.last_updated {
-                if now - last_updated < one_day {
-                    continue;
-                }
-            }
-
-            // Skip if recent error (< 1 hour)
-            if script.last_fetch_error.is_some() {
-                if let Some(last_updated) = script.last_updated {
-                    if now - last_updated < one_hour {
-                        continue;
-                    }
-                }
-            }
+    // "New Script", "New Script 2", ... so several new scripts can be told apart
+    let name = (1..)
+        .map(|n| if n == 1 { "New Script".to_string() } else { format!("New Script {}", n) })
+        .find(|name| !scripts.iter().any(|s| &s.name == name))
+        .unwrap();
+    let mut new_script = script_with_dependencies(new_script_template(&name), None, &mut dependencies, &mut resources)?;
 
-            // Try to fetch update
-            match fetch_script_with_dependencies(url, &mut dependencies) {
-                Ok(updated) => {
-                    // Preserve user settings
-                    script.code = updated.code;
-                    script.name = updated.name;
-                    script.version = updated.version;
-                    script.description = updated.description;
-                    script.author = updated.author;
-                    script.requires = updated.requires;
-                    script.last_updated = Some(now);
-                    script.last_fetch_error = None;
-                    updated_count += 1;
-                }
-                Err(e) => {
-                    script.last_fetch_error = Some(e);
-                    script.last_updated = Some(now);
-                }
-            }
-        }
-    }
+    // Assign order (highest + 1)
+    let max_order = scripts.iter().map(|s| s.order).max().unwrap_or(-1);
+    new_script.order = max_order + 1;
 
-    let scripts_clone = scripts.clone();
-    let dependencies_clone = dependencies.clone();
-    drop(scripts);
-    drop(dependencies);
-    state.save_scripts(&scripts_clone)?;
-    state.save_dependencies(&dependencies_clone)?;
+    scripts.push(new_script.clone());
+    state.save_scripts(&scripts)?;
 
-    Ok(updated_count)
+    Ok(new_script)
 }
 
+// Save code from the editor, picking up header changes and any new @require/@resource
 #[tauri::command]
-fn get_data_dir(state: tauri::State<AppState>) -> Result<String, String> {
-    Ok(state.data_dir.to_string_lossy().to_string())
+fn update_script_code(
+    id: String,
+    code: String,
+    window: tauri::WebviewWindow,
+    state: tauri::State<AppState>
+) -> Result<UserScript, String> {
+    if !MANAGER_WINDOW_LABELS.contains(&window.label()) {
+        return Err("Scripts can only be edited from the manager".to_string());
+    }
+
+    state.set_script_code(&id, code, "edit")
 }
 
-// Discord Rich Presence commands
+// Point a script at a file on disk, which is then watched and reloaded on every save.
+// The user always picks the file, so no page can have a file of its choosing read
 #[tauri::command]
-async fn discord_connect(state: tauri::State<'_, AppState>) -> Result<(), String> {
-    // Run blocking Discord IPC connection in a separate thread
+async fn link_script_file(
+    id: String,
+    window: tauri::WebviewWindow,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>
+) -> Result<Option<UserScript>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    if !MANAGER_WINDOW_LABELS.contains(&window.label()) {
+        return Err("Scripts can only be linked from the manager".to_string());
+    }
+
+    let dialog = app.dialog()
+        .file()
+        .set_title("Link userscript file")
+        .add_filter("Userscripts", &["js"]);
+    let picked = tauri::async_runtime::spawn_blocking(move || dialog.blocking_pick_file())
+        .await
+        .ok()
+        .flatten()
+        .and_then(|path| path.into_path().ok());
+    let Some(path) = picked else {
+        return Ok(None);
+    };
+
+    // Watchers report canonical paths, so store the link the same way
+    let path = fs::canonicalize(&path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let code = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    state.set_script_code(&id, code, "file")?;
+
+    let linked = {
+        let mut scripts = state.scripts.lock().unwrap();
+        let script = scripts.iter_mut()
+            .find(|s| s.id == id)
+            .ok_or_else(|| "Script not found".to_string())?;
+        script.local_path = Some(path.to_string_lossy().to_string());
+        script.last_fetch_error = None;
+        script.pending_update = None;
+        let linked = script.clone();
+        state.save_scripts(&scripts)?;
+        linked
+    };
+
+    watch_linked_scripts(&app)?;
+    Ok(Some(linked))
+}
+
+#[tauri::command]
+fn unlink_script_file(id: String, app: tauri::AppHandle, state: tauri::State<AppState>) -> Result<UserScript, String> {
+    let unlinked = {
+        let mut scripts = state.scripts.lock().unwrap();
+        let script = scripts.iter_mut()
+            .find(|s| s.id == id)
+            .ok_or_else(|| "Script not found".to_string())?;
+        script.local_path = None;
+        let unlinked = script.clone();
+        state.save_scripts(&scripts)?;
+        unlinked
+    };
+
+    watch_linked_scripts(&app)?;
+    Ok(unlinked)
+}
+
+// Watch the folders of all file-linked scripts, replacing the previous watcher. Folders rather
+// than files, since editors often save by writing a new file and renaming it over the old one
+fn watch_linked_scripts(app: &tauri::AppHandle) -> Result<(), String> {
+    use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode, DebounceEventResult};
+
+    let state = app.state::<AppState>();
+    let dirs: HashSet<PathBuf> = state.scripts.lock().unwrap()
+        .iter()
+        .filter_map(|s| s.local_path.as_ref())
+        .filter_map(|path| PathBuf::from(path).parent().map(|dir| dir.to_path_buf()))
+        .collect();
+
+    let mut watcher = state.script_watcher.lock().unwrap();
+    *watcher = None;
+    if dirs.is_empty() {
+        return Ok(());
+    }
+
+    let handle = app.clone();
+    let mut debouncer = new_debouncer(std::time::Duration::from_millis(300), move |result: DebounceEventResult| {
+        match result {
+            Ok(events) => {
+                let changed: Vec<PathBuf> = events.into_iter().map(|event| event.path).collect();
+                sync_linked_scripts(&handle, &changed);
+            }
+            Err(e) => eprintln!("[Script watcher] {}", e),
+        }
+    })
+    .map_err(|e| format!("Failed to start file watcher: {}", e))?;
+
+    for dir in &dirs {
+        if let Err(e) = debouncer.watcher().watch(dir, RecursiveMode::NonRecursive) {
+            eprintln!("[Script watcher] Failed to watch {}: {}", dir.display(), e);
+        }
+    }
+    *watcher = Some(debouncer);
+    Ok(())
+}
+
+// Re-read linked scripts whose file changed, then reload the GeoGuessr window so they run
+fn sync_linked_scripts(app: &tauri::AppHandle, changed: &[PathBuf]) {
+    let state = app.state::<AppState>();
+    let linked: Vec<(String, String, String)> = state.scripts.lock().unwrap()
+        .iter()
+        .filter_map(|s| s.local_path.as_ref().map(|path| (s.id.clone(), path.clone(), s.code.clone())))
+        .filter(|(_, path, _)| changed.iter().any(|changed| {
+            changed == std::path::Path::new(path)
+                || fs::canonicalize(changed).map(|c| c == std::path::Path::new(path)).unwrap_or(false)
+        }))
+        .collect();
+
+    let mut updated = false;
+    for (id, path, current_code) in linked {
+        // Missing files are usually an editor halfway through saving, the next event picks it up
+        let code = match fs::read_to_string(&path) {
+            Ok(code) => code,
+            Err(_) => continue,
+        };
+        if code == current_code {
+            continue;
+        }
+
+        let result = state.set_script_code(&id, code, "file");
+        let mut scripts = state.scripts.lock().unwrap();
+        if let Some(script) = scripts.iter_mut().find(|s| s.id == id) {
+            script.last_fetch_error = result.as_ref()
+                .err()
+                .map(|e| format!("Failed to load {}: {}", path, e));
+            let _ = state.save_scripts(&scripts);
+        }
+        updated |= result.is_ok();
+    }
+
+    if !updated {
+        return;
+    }
+    let _ = app.emit("scripts_changed", ());
+    if !game_windows(app, &state).is_empty() {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let state = app.state::<AppState>();
+            if let Err(e) = reload_scripts(app.clone(), state).await {
+                eprintln!("[Script watcher] Reload failed: {}", e);
+            }
+        });
+    }
+}
+
+#[tauri::command]
+fn reorder_script(id: String, new_order: i32, state: tauri::State<AppState>) -> Result<(), String> {
+    let mut scripts = state.scripts.lock().unwrap();
+
+    if let Some(script) = scripts.iter_mut().find(|s| s.id == id) {
+        script.order = new_order;
+        state.save_scripts(&scripts)?;
+        Ok(())
+    } else {
+        Err("Script not found".to_string())
+    }
+}
+
+#[tauri::command]
+fn toggle_script(id: String, enabled: bool, state: tauri::State<AppState>) -> Result<(), String> {
+    let mut scripts = state.scripts.lock().unwrap();
+
+    if let Some(script) = scripts.iter_mut().find(|s| s.id == id) {
+        script.enabled = enabled;
+        state.save_scripts(&scripts)?;
+        Ok(())
+    } else {
+        Err("Script not found".to_string())
+    }
+}
+
+#[tauri::command]
+fn delete_script(id: String, state: tauri::State<AppState>) -> Result<(), String> {
+    let mut scripts = state.scripts.lock().unwrap();
+    if !scripts.iter().any(|s| s.id == id) {
+        return Err("Script not found".to_string());
+    }
+    scripts.retain(|s| s.id != id);
+    state.save_scripts(&scripts)?;
+
+    let mut dependencies = state.dependencies.lock().unwrap();
+    let mut resources = state.resources.lock().unwrap();
+    if collect_unused_dependencies(&scripts, &mut dependencies, &mut resources) {
+        state.save_dependencies(&dependencies)?;
+        state.save_resources(&resources)?;
+    }
+    drop(scripts);
+    drop(dependencies);
+    drop(resources);
+
+    // Drop the script's stored GM values and history along with it
+    let mut values = state.values.lock().unwrap();
+    if values.remove(&id).is_some() {
+        state.save_values(&values)?;
+    }
+    let history_file = state.history_file(&id)?;
+    if history_file.exists() {
+        fs::remove_file(&history_file)
+            .map_err(|e| format!("Failed to remove history file: {}", e))?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn refresh_script(id: String, state: tauri::State<AppState>) -> Result<UserScript, String> {
+    use chrono::Utc;
+
+    let mut scripts = state.scripts.lock().unwrap();
+    let mut dependencies = state.dependencies.lock().unwrap();
+    let mut resources = state.resources.lock().unwrap();
+
+    // Find script
+    let script_index = scripts.iter().position(|s| s.id == id)
+        .ok_or_else(|| "Script not found".to_string())?;
+    if scripts[script_index].local_path.is_some() {
+        return Err("This script is linked to a local file, unlink it to update from its URL".to_string());
+    }
+
+    // Fetch fresh copy, unless the server says ours is current. Failures are kept on the
+    // script so the manager can show them
+    let fetched = match fetch_script_update(&scripts[script_index], &mut dependencies, &mut resources) {
+        Ok(fetched) => fetched,
+        Err(e) => {
+            scripts[script_index].last_fetch_error = Some(e.clone());
+            state.save_scripts(&scripts)?;
+            return Err(e);
+        }
+    };
+
+    // A new version waits for review. Asking by hand offers a rejected one again
+    let now = Utc::now().timestamp() as u64;
+    let script = &mut scripts[script_index];
+    if let Some((fetched, refreshed)) = fetched {
+        script.rejected_update = None;
+        stage_script_update(script, fetched, refreshed, now);
+    }
+    script.last_updated = Some(now);
+    script.last_fetch_error = None;
+    let updated_script = script.clone();
+    collect_unused_dependencies(&scripts, &mut dependencies, &mut resources);
+
+    let scripts_clone = scripts.clone();
+    let dependencies_clone = dependencies.clone();
+    let resources_clone = resources.clone();
+    drop(scripts);
+    drop(dependencies);
+    drop(resources);
+    state.save_scripts(&scripts_clone)?;
+    state.save_dependencies(&dependencies_clone)?;
+    state.save_resources(&resources_clone)?;
+
+    Ok(updated_script)
+}
+
+// Keep a freshly fetched version of a script, and of its @require files, as its pending update
+// instead of running it. Returns whether there is something new to review
+fn stage_script_update(
+    script: &mut UserScript,
+    fetched: UserScript,
+    dependencies: HashMap<String, ScriptDependency>,
+    now: u64
+) -> bool {
+    if fetched.code == script.code && dependencies.is_empty() {
+        // Same code behind new validators, e.g. after the server was redeployed
+        script.etag = fetched.etag;
+        script.last_modified = fetched.last_modified;
+        script.pending_update = None;
+        return false;
+    }
+    let hash = update_hash(&fetched.code, &dependencies);
+    if script.rejected_update.as_deref() == Some(hash.as_str()) {
+        return false;
+    }
+    if script.pending_update.as_ref().is_some_and(|pending| update_hash(&pending.code, &pending.dependencies) == hash) {
+        return false;
+    }
+
+    script.pending_update = Some(PendingUpdate {
+        code: fetched.code,
+        version: fetched.version,
+        etag: fetched.etag,
+        last_modified: fetched.last_modified,
+        dependencies,
+        fetched_at: now,
+    });
+    true
+}
+
+// What rejected_update remembers of an update: the hash of its code, and of the @require
+// content that came with it if any did
+fn update_hash(code: &str, dependencies: &HashMap<String, ScriptDependency>) -> String {
+    let mut urls: Vec<&String> = dependencies.keys().collect();
+    urls.sort();
+    let mut hashed = code.to_string();
+    for url in urls {
+        hashed.push_str(&format!("\n{}\n{}", url, dependencies[url].code));
+    }
+    code_hash(&hashed)
+}
+
+// Hex SHA-256 of a script's code
+fn code_hash(code: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(code.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// A script's token in a window: hex SHA-256 of "<window key>:<script id>". The window's bootstrap
+// script derives the same tokens for scripts it gets after a hot reload
+fn script_token(window_key: &str, script_id: &str) -> String {
+    code_hash(&format!("{}:{}", window_key, script_id))
+}
+
+// Hex HMAC-SHA256, signing the scripts a hot reload leaves in a page's sessionStorage. The
+// bootstrap script checks it with the same construction in JS
+fn hmac_sha256(key: &str, message: &str) -> String {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(message.as_bytes());
+    mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Check scripts not checked for a day for updates. Returns how many have a new update to review
+#[tauri::command]
+fn auto_update_scripts(state: tauri::State<AppState>) -> Result<usize, String> {
+    use chrono::Utc;
+
+    let mut scripts = state.scripts.lock().unwrap();
+    let mut dependencies = state.dependencies.lock().unwrap();
+    let mut resources = state.resources.lock().unwrap();
+
+    let now = Utc::now().timestamp() as u64;
+    let one_day = 24 * 60 * 60;
+    let one_hour = 60 * 60;
+    let mut updated_count = 0;
+
+    for script in scripts.iter_mut() {
+        // Only update scripts with URLs that aren't pinned to their current version. A script
+        // linked to a local file gets its code from that file
+        if script.url.is_some() && !script.pinned && script.local_path.is_none() {
+            // Skip if updated recently (< 24 hours)
+            if let Some(last_updated) = script.last_updated {
+                if now - last_updated < one_day {
+                    continue;
+                }
+            }
+
+            // Skip if recent error (< 1 hour)
+            if script.last_fetch_error.is_some() {
+                if let Some(last_updated) = script.last_updated {
+                    if now - last_updated < one_hour {
+                        continue;
+                    }
+                }
+            }
+
+            // Try to fetch update, which then waits for the user to review it
+            match fetch_script_update(script, &mut dependencies, &mut resources) {
+                Ok(Some((updated, refreshed))) => {
+                    if stage_script_update(script, updated, refreshed, now) {
+                        updated_count += 1;
+                    }
+                    script.last_updated = Some(now);
+                    script.last_fetch_error = None;
+                }
+                Ok(None) => {
+                    script.last_updated = Some(now);
+                    script.last_fetch_error = None;
+                }
+                Err(e) => {
+                    script.last_fetch_error = Some(e);
+                    script.last_updated = Some(now);
+                }
+            }
+        }
+    }
+    collect_unused_dependencies(&scripts, &mut dependencies, &mut resources);
+
+    let scripts_clone = scripts.clone();
+    let dependencies_clone = dependencies.clone();
+    let resources_clone = resources.clone();
+    drop(scripts);
+    drop(dependencies);
+    drop(resources);
+    state.save_scripts(&scripts_clone)?;
+    state.save_dependencies(&dependencies_clone)?;
+    state.save_resources(&resources_clone)?;
+
+    Ok(updated_count)
+}
+
+// One line of an update diff, with 1-based line numbers in the current and the new code
+#[derive(Debug, Clone, Serialize)]
+struct DiffLine {
+    // "equal", "delete" or "insert"
+    tag: &'static str,
+    old_line: Option<usize>,
+    new_line: Option<usize>,
+    text: String,
+}
+
+// Values of a metadata key the update adds or removes
+#[derive(Debug, Clone, Serialize)]
+struct MetadataChange {
+    key: &'static str,
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ScriptUpdateDiff {
+    current_version: Option<String>,
+    new_version: Option<String>,
+    metadata: Vec<MetadataChange>,
+    // @require URLs whose content changed since they were cached
+    refreshed_requires: Vec<String>,
+    // Changed lines with three lines of context, one hunk per changed region
+    hunks: Vec<Vec<DiffLine>>,
+}
+
+fn metadata_change(key: &'static str, old: &[String], new: &[String]) -> Option<MetadataChange> {
+    let added: Vec<String> = new.iter().filter(|v| !old.contains(v)).cloned().collect();
+    let removed: Vec<String> = old.iter().filter(|v| !new.contains(v)).cloned().collect();
+    if added.is_empty() && removed.is_empty() {
+        return None;
+    }
+    Some(MetadataChange { key, added, removed })
+}
+
+// Compare a script with its pending update, line by line and by header
+#[tauri::command]
+fn get_script_update_diff(id: String, state: tauri::State<AppState>) -> Result<ScriptUpdateDiff, String> {
+    use similar::{ChangeTag, TextDiff};
+
+    let scripts = state.scripts.lock().unwrap();
+    let script = scripts.iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| "Script not found".to_string())?;
+    let pending = script.pending_update.as_ref()
+        .ok_or_else(|| "No update waiting for this script".to_string())?;
+
+    let old = parse_metadata(&script.code);
+    let new = parse_metadata(&pending.code);
+    let resource_values = |metadata: &ScriptMetadata| -> Vec<String> {
+        metadata.resources.iter().map(|r| format!("{} {}", r.name, r.url)).collect()
+    };
+    let run_at_values = |metadata: &ScriptMetadata| -> Vec<String> {
+        metadata.run_at.iter().cloned().collect()
+    };
+
+    // Permission-like keys first, they're what a reviewer should look at
+    let metadata = [
+        metadata_change("grant", &old.grants, &new.grants),
+        metadata_change("connect", &old.connects, &new.connects),
+        metadata_change("require", &old.requires, &new.requires),
+        metadata_change("resource", &resource_values(&old), &resource_values(&new)),
+        metadata_change("match", &old.matches, &new.matches),
+        metadata_change("include", &old.includes, &new.includes),
+        metadata_change("exclude", &old.excludes, &new.excludes),
+        metadata_change("exclude-match", &old.exclude_matches, &new.exclude_matches),
+        metadata_change("run-at", &run_at_values(&old), &run_at_values(&new)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    let mut refreshed_requires: Vec<String> = pending.dependencies.keys().cloned().collect();
+    refreshed_requires.sort();
+
+    let diff = TextDiff::from_lines(&script.code, &pending.code);
+    let hunks = diff.grouped_ops(3)
+        .iter()
+        .map(|group| {
+            group.iter()
+                .flat_map(|op| diff.iter_changes(op))
+                .map(|change| DiffLine {
+                    tag: match change.tag() {
+                        ChangeTag::Equal => "equal",
+                        ChangeTag::Delete => "delete",
+                        ChangeTag::Insert => "insert",
+                    },
+                    old_line: change.old_index().map(|i| i + 1),
+                    new_line: change.new_index().map(|i| i + 1),
+                    text: change.value().trim_end_matches(['\r', '\n']).to_string(),
+                })
+                .collect()
+        })
+        .collect();
+
+    Ok(ScriptUpdateDiff {
+        current_version: script.version.clone(),
+        new_version: pending.version.clone(),
+        metadata,
+        refreshed_requires,
+        hunks,
+    })
+}
+
+// Replace a script's code with its pending update
+#[tauri::command]
+fn accept_script_update(
+    id: String,
+    window: tauri::WebviewWindow,
+    state: tauri::State<AppState>
+) -> Result<UserScript, String> {
+    use chrono::Utc;
+
+    if !MANAGER_WINDOW_LABELS.contains(&window.label()) {
+        return Err("Updates can only be accepted from the manager".to_string());
+    }
+
+    let mut scripts = state.scripts.lock().unwrap();
+    let mut dependencies = state.dependencies.lock().unwrap();
+    let mut resources = state.resources.lock().unwrap();
+
+    let script = scripts.iter_mut()
+        .find(|s| s.id == id)
+        .ok_or_else(|| "Script not found".to_string())?;
+    let pending = script.pending_update.clone()
+        .ok_or_else(|| "No update waiting for this script".to_string())?;
+
+    // Dependencies were fetched along with the update but may have been cleaned up since
+    let metadata = parse_metadata(&pending.code);
+    resolve_dependencies(&metadata, &mut dependencies, &mut resources)?;
+    dependencies.extend(pending.dependencies);
+    if pending.code != script.code {
+        state.record_revision(script, "update")?;
+    }
+
+    script.apply_metadata(metadata);
+    script.code = pending.code;
+    script.etag = pending.etag;
+    script.last_modified = pending.last_modified;
+    script.last_updated = Some(Utc::now().timestamp() as u64);
+    script.last_fetch_error = None;
+    script.pending_update = None;
+    script.rejected_update = None;
+    let accepted = script.clone();
+    collect_unused_dependencies(&scripts, &mut dependencies, &mut resources);
+
+    let scripts_clone = scripts.clone();
+    let dependencies_clone = dependencies.clone();
+    let resources_clone = resources.clone();
+    drop(scripts);
+    drop(dependencies);
+    drop(resources);
+    state.save_scripts(&scripts_clone)?;
+    state.save_dependencies(&dependencies_clone)?;
+    state.save_resources(&resources_clone)?;
+
+    Ok(accepted)
+}
+
+// Drop a script's pending update and don't offer that version again
+#[tauri::command]
+fn reject_script_update(
+    id: String,
+    window: tauri::WebviewWindow,
+    state: tauri::State<AppState>
+) -> Result<UserScript, String> {
+    if !MANAGER_WINDOW_LABELS.contains(&window.label()) {
+        return Err("Updates can only be rejected from the manager".to_string());
+    }
+
+    let mut scripts = state.scripts.lock().unwrap();
+    let mut dependencies = state.dependencies.lock().unwrap();
+    let mut resources = state.resources.lock().unwrap();
+
+    let script = scripts.iter_mut()
+        .find(|s| s.id == id)
+        .ok_or_else(|| "Script not found".to_string())?;
+    let pending = script.pending_update.take()
+        .ok_or_else(|| "No update waiting for this script".to_string())?;
+    script.rejected_update = Some(update_hash(&pending.code, &pending.dependencies));
+    let rejected = script.clone();
+    collect_unused_dependencies(&scripts, &mut dependencies, &mut resources);
+
+    let scripts_clone = scripts.clone();
+    let dependencies_clone = dependencies.clone();
+    let resources_clone = resources.clone();
+    drop(scripts);
+    drop(dependencies);
+    drop(resources);
+    state.save_scripts(&scripts_clone)?;
+    state.save_dependencies(&dependencies_clone)?;
+    state.save_resources(&resources_clone)?;
+
+    Ok(rejected)
+}
+
+#[tauri::command]
+fn get_script_history(id: String, state: tauri::State<AppState>) -> Result<Vec<ScriptRevision>, String> {
+    if !state.scripts.lock().unwrap().iter().any(|s| s.id == id) {
+        return Err("Script not found".to_string());
+    }
+    Ok(state.load_history(&id))
+}
+
+// Put an earlier version of a script back. The current one is kept in the history, so a
+// rollback can be undone. Pinning stops auto_update_scripts from offering the newer version
+#[tauri::command]
+fn rollback_script(
+    id: String,
+    revision_id: String,
+    pin: bool,
+    window: tauri::WebviewWindow,
+    state: tauri::State<AppState>,
+) -> Result<UserScript, String> {
+    if !MANAGER_WINDOW_LABELS.contains(&window.label()) {
+        return Err("Scripts can only be rolled back from the manager".to_string());
+    }
+
+    let revision = state.load_history(&id)
+        .into_iter()
+        .find(|r| r.id == revision_id)
+        .ok_or_else(|| "Revision not found".to_string())?;
+
+    // A waiting update is what was just rolled away from, so don't offer it again
+    let pending_hash = state.scripts.lock().unwrap()
+        .iter()
+        .find(|s| s.id == id)
+        .and_then(|s| s.pending_update.as_ref())
+        .map(|pending| update_hash(&pending.code, &pending.dependencies));
+    state.set_script_code(&id, revision.code, "rollback")?;
+
+    let mut scripts = state.scripts.lock().unwrap();
+    let script = scripts.iter_mut()
+        .find(|s| s.id == id)
+        .ok_or_else(|| "Script not found".to_string())?;
+    if pending_hash.is_some() {
+        script.rejected_update = pending_hash;
+    }
+    if pin {
+        script.pinned = true;
+    }
+    let rolled_back = script.clone();
+    state.save_scripts(&scripts)?;
+    Ok(rolled_back)
+}
+
+#[tauri::command]
+fn set_script_pinned(
+    id: String,
+    pinned: bool,
+    window: tauri::WebviewWindow,
+    state: tauri::State<AppState>
+) -> Result<UserScript, String> {
+    if !MANAGER_WINDOW_LABELS.contains(&window.label()) {
+        return Err("Scripts can only be pinned from the manager".to_string());
+    }
+
+    let mut scripts = state.scripts.lock().unwrap();
+    let script = scripts.iter_mut()
+        .find(|s| s.id == id)
+        .ok_or_else(|| "Script not found".to_string())?;
+    script.pinned = pinned;
+    let updated = script.clone();
+    state.save_scripts(&scripts)?;
+    Ok(updated)
+}
+
+// Broadcast to every window so GM_addValueChangeListener fires for remote changes. Every page
+// can listen to events, so only which key changed goes out. The script's own value store
+// fetches the value with its token
+#[derive(Debug, Clone, Serialize)]
+struct GmValueChange {
+    script_id: String,
+    key: String,
+    origin: String,
+}
+
+// GM value storage - each script only sees the values stored under its own id
+#[tauri::command]
+fn gm_get_values(
+    script_token: String,
+    window: tauri::WebviewWindow,
+    state: tauri::State<AppState>
+) -> Result<HashMap<String, serde_json::Value>, String> {
+    let script_id = state.token_script_id(&window, &script_token)?;
+    let values = state.values.lock().unwrap();
+    Ok(values.get(&script_id).cloned().unwrap_or_default())
+}
+
+#[tauri::command]
+fn gm_set_value(
+    script_token: String,
+    key: String,
+    value: serde_json::Value,
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: tauri::State<AppState>
+) -> Result<(), String> {
+    let script_id = state.token_script_id(&window, &script_token)?;
+    let mut values = state.values.lock().unwrap();
+    values.entry(script_id.clone()).or_default().insert(key.clone(), value);
+    state.save_values(&values)?;
+    drop(values);
+
+    let _ = app.emit("gm_value_changed", GmValueChange {
+        script_id,
+        key,
+        origin: window.label().to_string(),
+    });
+    Ok(())
+}
+
+#[tauri::command]
+fn gm_delete_value(
+    script_token: String,
+    key: String,
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: tauri::State<AppState>
+) -> Result<(), String> {
+    let script_id = state.token_script_id(&window, &script_token)?;
+    let mut values = state.values.lock().unwrap();
+    let removed = values.get_mut(&script_id).and_then(|script_values| script_values.remove(&key));
+    if removed.is_none() {
+        return Ok(());
+    }
+    state.save_values(&values)?;
+    drop(values);
+
+    let _ = app.emit("gm_value_changed", GmValueChange {
+        script_id,
+        key,
+        origin: window.label().to_string(),
+    });
+    Ok(())
+}
+
+// A GM_registerMenuCommand entry, tied to the window whose page registered it
+#[derive(Debug, Clone, Serialize)]
+struct MenuCommand {
+    window: String,
+    script_id: String,
+    command_id: String,
+    caption: String,
+}
+
+#[tauri::command]
+fn gm_register_menu_command(
+    script_token: String,
+    command_id: String,
+    caption: String,
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: tauri::State<AppState>
+) -> Result<(), String> {
+    let script_id = state.token_script_id(&window, &script_token)?;
+    let mut menu_commands = state.menu_commands.lock().unwrap();
+    let label = window.label().to_string();
+    menu_commands.retain(|c| !(c.window == label && c.script_id == script_id && c.command_id == command_id));
+    menu_commands.push(MenuCommand { window: label, script_id, command_id, caption });
+    drop(menu_commands);
+
+    let _ = app.emit("gm_menu_commands_changed", ());
+    Ok(())
+}
+
+#[tauri::command]
+fn gm_unregister_menu_command(
+    script_token: String,
+    command_id: String,
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: tauri::State<AppState>
+) -> Result<(), String> {
+    let script_id = state.token_script_id(&window, &script_token)?;
+    let mut menu_commands = state.menu_commands.lock().unwrap();
+    menu_commands.retain(|c| !(c.window == window.label() && c.script_id == script_id && c.command_id == command_id));
+    drop(menu_commands);
+
+    let _ = app.emit("gm_menu_commands_changed", ());
+    Ok(())
+}
+
+// Called by the loader on every page load, the previous page's commands are gone
+#[tauri::command]
+fn gm_clear_menu_commands(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: tauri::State<AppState>
+) -> Result<(), String> {
+    let mut menu_commands = state.menu_commands.lock().unwrap();
+    menu_commands.retain(|c| c.window != window.label());
+    drop(menu_commands);
+
+    let _ = app.emit("gm_menu_commands_changed", ());
+    Ok(())
+}
+
+#[tauri::command]
+fn get_menu_commands(state: tauri::State<AppState>) -> Result<Vec<MenuCommand>, String> {
+    Ok(state.menu_commands.lock().unwrap().clone())
+}
+
+// Run a menu command from the manager window inside the page that registered it
+#[tauri::command]
+fn run_menu_command(window: String, script_id: String, command_id: String, app: tauri::AppHandle) -> Result<(), String> {
+    app.emit_to(window.as_str(), "gm_menu_command", serde_json::json!({
+        "script_id": script_id,
+        "command_id": command_id,
+    }))
+    .map_err(|e| format!("Failed to run menu command: {}", e))
+}
+
+#[tauri::command]
+fn get_settings(state: tauri::State<AppState>) -> Result<AppSettings, String> {
+    Ok(state.settings.lock().unwrap().clone())
+}
+
+#[tauri::command]
+fn update_settings(settings: AppSettings, state: tauri::State<AppState>) -> Result<(), String> {
+    let mut current = state.settings.lock().unwrap();
+    *current = settings;
+    state.save_settings(&current)
+}
+
+#[tauri::command]
+fn get_data_dir(state: tauri::State<AppState>) -> Result<String, String> {
+    Ok(state.data_dir.to_string_lossy().to_string())
+}
+
+// Discord Rich Presence commands
+#[tauri::command]
+async fn discord_connect(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    // Run blocking Discord IPC connection in a separate thread
     let client_result = tokio::task::spawn_blocking(move || {
         let mut client = DiscordIpcClient::new(DISCORD_APP_ID);
         client.connect()
@@ -523,21 +2523,179 @@ fn discord_disconnect(state: tauri::State<'_, AppState>) -> Result<(), String> {
     Ok(())
 }
 
-#[tauri::command]
-async fn open_geoguessr(app: tauri::AppHandle, state: tauri::State<'_, AppState>) -> Result<(), String> {
-    if let Some(_window) = app.get_webview_window("geoguessr") {
-        return Ok(());
+// How much of a window's title strip has to be on a monitor for its saved position to be reused
+const WINDOW_VISIBLE_WIDTH: f64 = 100.0;
+const WINDOW_TITLE_HEIGHT: f64 = 40.0;
+
+// Whether a saved window would still be on a connected monitor with enough of its top edge
+// showing to be dragged, so monitors that were unplugged don't leave it off-screen
+fn window_geometry_on_screen(app: &tauri::AppHandle, geometry: &WindowGeometry) -> bool {
+    let monitors = app.available_monitors().unwrap_or_default();
+
+    if let Some(name) = &geometry.monitor {
+        if !monitors.iter().any(|monitor| monitor.name() == Some(name)) {
+            return false;
+        }
+    }
+
+    monitors.iter().any(|monitor| {
+        let scale = monitor.scale_factor();
+        let position = monitor.position().to_logical::<f64>(scale);
+        let size = monitor.size().to_logical::<f64>(scale);
+
+        let visible_width = (geometry.x + geometry.width).min(position.x + size.width) - geometry.x.max(position.x);
+        let visible_height = (geometry.y + WINDOW_TITLE_HEIGHT).min(position.y + size.height) - geometry.y.max(position.y);
+        visible_width >= WINDOW_VISIBLE_WIDTH.min(geometry.width) && visible_height > 0.0
+    })
+}
+
+// Size and place a new window the way it was left. If its monitor is gone only the
+// maximized/fullscreen state is restored and the window keeps its default size and placement
+fn apply_window_geometry<'a>(
+    builder: WebviewWindowBuilder<'a, tauri::Wry, tauri::AppHandle>,
+    app: &tauri::AppHandle,
+    label: &str,
+) -> WebviewWindowBuilder<'a, tauri::Wry, tauri::AppHandle> {
+    let state = app.state::<AppState>();
+    let Some(geometry) = state.window_geometry.lock().unwrap().get(label).cloned() else {
+        return builder;
+    };
+
+    let builder = builder
+        .maximized(geometry.maximized)
+        .fullscreen(geometry.fullscreen);
+
+    if window_geometry_on_screen(app, &geometry) {
+        builder
+            .inner_size(geometry.width, geometry.height)
+            .position(geometry.x, geometry.y)
+    } else {
+        builder
+    }
+}
+
+// Record a window's current geometry. Saves are throttled to one a second while the window
+// is being dragged or resized, `force` writes it out straight away
+fn capture_window_geometry(window: &tauri::WebviewWindow, force: bool) {
+    use std::time::Duration;
+
+    // Minimized windows report a bogus size and position on some platforms
+    if window.is_minimized().unwrap_or(false) {
+        return;
+    }
+
+    let state = window.state::<AppState>();
+    let maximized = window.is_maximized().unwrap_or(false);
+    let fullscreen = window.is_fullscreen().unwrap_or(false);
+    let monitor = window.current_monitor().ok().flatten().and_then(|monitor| monitor.name().cloned());
+
+    let mut window_geometry = state.window_geometry.lock().unwrap();
+    let geometry = match window_geometry.get(window.label()).cloned() {
+        // Keep the normal bounds while maximized or fullscreen so un-maximizing after a restore
+        // goes back to them instead of to a full-screen sized window
+        Some(previous) if maximized || fullscreen => WindowGeometry { maximized, fullscreen, monitor, ..previous },
+        _ => {
+            let (Ok(size), Ok(position)) = (window.inner_size(), window.outer_position()) else {
+                return;
+            };
+            let scale = window.scale_factor().unwrap_or(1.0);
+            let size = size.to_logical::<f64>(scale);
+            let position = position.to_logical::<f64>(scale);
+
+            WindowGeometry {
+                width: size.width,
+                height: size.height,
+                x: position.x,
+                y: position.y,
+                maximized,
+                fullscreen,
+                monitor,
+            }
+        }
+    };
+    window_geometry.insert(window.label().to_string(), geometry);
+
+    let recently_saved = state.window_geometry_saved_at.lock().unwrap()
+        .is_some_and(|saved_at| saved_at.elapsed() < Duration::from_secs(1));
+    if force || !recently_saved {
+        if let Err(e) = state.save_window_geometry(&window_geometry) {
+            eprintln!("[Window state] {}", e);
+        }
+    }
+}
+
+// Keep a window's saved geometry up to date as it's moved, resized and closed
+fn track_window_geometry(window: &tauri::WebviewWindow) {
+    let tracked = window.clone();
+    window.on_window_event(move |event| match event {
+        tauri::WindowEvent::Moved(_)
+        | tauri::WindowEvent::Resized(_)
+        | tauri::WindowEvent::ScaleFactorChanged { .. } => capture_window_geometry(&tracked, false),
+        tauri::WindowEvent::CloseRequested { .. } => capture_window_geometry(&tracked, true),
+        _ => {}
+    });
+}
+
+// What a GeoGuessr game window is built with. Everything else about the window is the same
+// for every game window and lives in build_game_window
+struct GameWindowConfig {
+    label: String,
+    title: String,
+    url: String,
+    // Inner size used when the window has no saved geometry
+    width: f64,
+    height: f64,
+    // Initialization script carrying the user's scripts, see get_bootstrap_script
+    init_script: String,
+    // Browser profile location, None for the default one. Windows and Linux keep profiles
+    // in a directory, macOS identifies them by a data store id
+    data_directory: Option<PathBuf>,
+    data_store_identifier: Option<[u8; 16]>,
+}
+
+impl GameWindowConfig {
+    // A window for an instance on the GeoGuessr home page with the default size
+    fn for_instance(state: &AppState, instance: &GameInstance) -> Self {
+        let title = if instance.label == MAIN_WINDOW_LABEL {
+            "GeoGuessr Desktop".to_string()
+        } else {
+            format!("GeoGuessr Desktop - {}", instance.name)
+        };
+
+        GameWindowConfig {
+            label: instance.label.clone(),
+            title,
+            url: "https://www.geoguessr.com/".to_string(),
+            width: 1400.0,
+            height: 900.0,
+            init_script: get_bootstrap_script(state, instance),
+            data_directory: instance.profile.as_ref().map(|profile| state.profile_dir(profile)),
+            data_store_identifier: instance.profile.as_ref()
+                .and_then(|profile| Uuid::parse_str(profile).ok())
+                .map(|profile| *profile.as_bytes()),
+        }
     }
+}
 
-    // Get all enabled scripts and combine them
-    let init_script = get_initialization_script(&state);
+// Create a GeoGuessr game window, restoring its saved geometry and keeping it up to date
+fn build_game_window(app: &tauri::AppHandle, config: GameWindowConfig) -> Result<tauri::WebviewWindow, String> {
+    let url: tauri::Url = config.url.parse()
+        .map_err(|e| format!("Invalid URL {}: {}", config.url, e))?;
+
+    let mut builder = WebviewWindowBuilder::new(app, &config.label, WebviewUrl::External(url))
+        .title(&config.title)
+        .inner_size(config.width, config.height);
+    if let Some(data_directory) = config.data_directory {
+        builder = builder.data_directory(data_directory);
+    }
+    if let Some(data_store_identifier) = config.data_store_identifier {
+        builder = builder.data_store_identifier(data_store_identifier);
+    }
 
-    let _window = WebviewWindowBuilder::new(&app, "geoguessr", WebviewUrl::External("https://www.geoguessr.com/".parse().unwrap()))
-        .title("GeoGuessr Desktop")
-        .inner_size(1400.0, 900.0)
+    let window = apply_window_geometry(builder, app, &config.label)
         .resizable(true)
         .decorations(false) // Custom titlebar
-        .initialization_script(&init_script)
+        .initialization_script(&config.init_script)
         .on_navigation(move |url| {
             // Allow navigation to geoguessr.com domains
             url.host_str() == Some("www.geoguessr.com") ||
@@ -545,26 +2703,306 @@ async fn open_geoguessr(app: tauri::AppHandle, state: tauri::State<'_, AppState>
         })
         .build()
         .map_err(|e| format!("Failed to create window: {}", e))?;
+    track_window_geometry(&window);
+
+    Ok(window)
+}
+
+// Open GeoGuessr windows of all instances
+fn game_windows(app: &tauri::AppHandle, state: &AppState) -> Vec<tauri::WebviewWindow> {
+    state.instances.lock().unwrap()
+        .iter()
+        .filter_map(|instance| app.get_webview_window(&instance.label))
+        .collect()
+}
+
+#[tauri::command]
+fn get_instances(state: tauri::State<AppState>) -> Result<Vec<GameInstance>, String> {
+    Ok(state.instances.lock().unwrap().clone())
+}
+
+// Add a GeoGuessr window with a fresh browser profile that runs every enabled script
+#[tauri::command]
+fn create_instance(name: String, state: tauri::State<AppState>) -> Result<GameInstance, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("The window needs a name".to_string());
+    }
+
+    let profile = Uuid::new_v4().to_string();
+    let instance = GameInstance {
+        label: format!("{}-{}", MAIN_WINDOW_LABEL, profile),
+        name: name.to_string(),
+        profile: Some(profile),
+        scripts: None,
+    };
+
+    let mut instances = state.instances.lock().unwrap();
+    instances.push(instance.clone());
+    state.save_instances(&instances)?;
+
+    Ok(instance)
+}
+
+// Rename an instance or change which scripts it runs. An open window is reloaded with its new scripts
+#[tauri::command]
+fn update_instance(
+    label: String,
+    name: String,
+    scripts: Option<Vec<String>>,
+    app: tauri::AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<GameInstance, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("The window needs a name".to_string());
+    }
+
+    let mut instances = state.instances.lock().unwrap();
+    let instance = instances.iter_mut()
+        .find(|i| i.label == label)
+        .ok_or_else(|| format!("No window {}", label))?;
+    let scripts_changed = instance.scripts != scripts;
+    instance.name = name.to_string();
+    instance.scripts = scripts;
+    let updated = instance.clone();
+    state.save_instances(&instances)?;
+    drop(instances);
+
+    if let Some(window) = app.get_webview_window(&label) {
+        if updated.label != MAIN_WINDOW_LABEL {
+            let _ = window.set_title(&format!("GeoGuessr Desktop - {}", updated.name));
+        }
+        if scripts_changed {
+            reload_game_window(&window, &state)?;
+        }
+    }
+
+    Ok(updated)
+}
+
+// Remove an instance along with its browser profile and saved geometry
+#[tauri::command]
+fn delete_instance(label: String, app: tauri::AppHandle, state: tauri::State<AppState>) -> Result<(), String> {
+    if label == MAIN_WINDOW_LABEL {
+        return Err("The main window can't be removed".to_string());
+    }
+
+    if let Some(window) = app.get_webview_window(&label) {
+        window.destroy().map_err(|e| format!("Failed to close window: {}", e))?;
+    }
+
+    let mut instances = state.instances.lock().unwrap();
+    let index = instances.iter()
+        .position(|i| i.label == label)
+        .ok_or_else(|| format!("No window {}", label))?;
+    let instance = instances.remove(index);
+    state.save_instances(&instances)?;
+    drop(instances);
+
+    let mut window_geometry = state.window_geometry.lock().unwrap();
+    if window_geometry.remove(&label).is_some() {
+        state.save_window_geometry(&window_geometry)?;
+    }
+    drop(window_geometry);
+
+    if let Some(profile) = instance.profile {
+        let profile_dir = state.profile_dir(&profile);
+        if profile_dir.exists() {
+            // The webview may still hold files open for a moment after closing, a leftover
+            // directory isn't used by anything else so it's only worth a warning
+            if let Err(e) = fs::remove_dir_all(&profile_dir) {
+                eprintln!("[Instances] Failed to remove {}: {}", profile_dir.display(), e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Open the window of an instance, the main one when no label is given
+#[tauri::command]
+async fn open_geoguessr(
+    label: Option<String>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    let label = label.unwrap_or_else(|| MAIN_WINDOW_LABEL.to_string());
+    if let Some(_window) = app.get_webview_window(&label) {
+        return Ok(());
+    }
+
+    let instance = state.instances.lock().unwrap()
+        .iter()
+        .find(|i| i.label == label)
+        .cloned()
+        .ok_or_else(|| format!("No window {}", label))?;
 
+    build_game_window(&app, GameWindowConfig::for_instance(&state, &instance))?;
     Ok(())
 }
 
-fn get_initialization_script(state: &AppState) -> String {
+// The initialization script GeoGuessr windows are built with. It carries the instance's
+// scripts as of the build and after a hot reload runs the ones reload_game_window left in
+// sessionStorage instead. Either way it fills in the scripts' tokens, derived from a key only
+// this script and Rust know
+fn get_bootstrap_script(state: &AppState, instance: &GameInstance) -> String {
+    let window_key = state.new_window_key(&instance.label);
+    let injection = get_initialization_script(state, instance);
+    let tokens: serde_json::Map<String, serde_json::Value> = injection.script_ids.iter()
+        .map(|id| (id.clone(), serde_json::Value::String(script_token(&window_key, id))))
+        .collect();
+    let payload = injection.code.replacen("__GG_SCRIPT_TOKENS__", &serde_json::Value::Object(tokens).to_string(), 1);
+
+    format!(r#"(function() {{
+  if (window !== window.top) return;
+
+  var stored = null;
+  try {{
+    stored = sessionStorage.getItem('gg_scripts');
+  }} catch (e) {{}}
+
+  if (!stored) {{
+{payload}
+    return;
+  }}
+
+  // Scripts changed since this window was built. Their tokens are derived here the way
+  // script_token does in Rust
+  var windowKey = '{window_key}';
+{sha256}
+  function hmacSha256(key, message) {{
+    var encoder = new TextEncoder();
+    var keyBytes = encoder.encode(key);
+    var block = new Uint8Array(64);
+    block.set(keyBytes.length > 64 ? sha256(keyBytes) : keyBytes);
+    function pad(byte, data) {{
+      var padded = new Uint8Array(64 + data.length);
+      for (var i = 0; i < 64; i++) padded[i] = block[i] ^ byte;
+      padded.set(data, 64);
+      return padded;
+    }}
+    return toHex(sha256(pad(0x5c, sha256(pad(0x36, encoder.encode(message))))));
+  }}
+  function scriptTokens(scriptIds) {{
+    var encoder = new TextEncoder();
+    var tokens = {{}};
+    scriptIds.forEach(function(id) {{
+      tokens[id] = toHex(sha256(encoder.encode(windowKey + ':' + id)));
+    }});
+    return JSON.stringify(tokens);
+  }}
+  function run(injection) {{
+    if (!document.documentElement) {{
+      setTimeout(function() {{ run(injection); }}, 1);
+      return;
+    }}
+    var script = document.createElement('script');
+    script.textContent = injection.code.replace('__GG_SCRIPT_TOKENS__', function() {{
+      return scriptTokens(injection.script_ids);
+    }});
+    script.setAttribute('data-geoguessr-desktop', 'loader');
+    document.documentElement.appendChild(script);
+    script.remove();
+  }}
+
+  // They didn't fit in sessionStorage. Fetching them runs them after the page's own
+  // scripts started, so a document-start script can't get ahead of the page this way
+  if (stored.indexOf('fetch:') === 0) {{
+    window.__TAURI__.core.invoke('get_injection_script', {{ fetchKey: stored.slice(6) }})
+      .then(run)
+      .catch(function(e) {{ console.error('[GeoGuessr Desktop] Failed to load scripts:', e); }});
+    return;
+  }}
+
+  // Run the stored scripts now, at document-start like built-in ones, unless the page
+  // changed them
+  try {{
+    var injection = JSON.parse(stored);
+    if (hmacSha256(windowKey, injection.script_ids.join(',') + '\n' + injection.code) === injection.mac) {{
+      run(injection);
+      return;
+    }}
+    console.warn('[GeoGuessr Desktop] Stored scripts were modified, not running them');
+  }} catch (e) {{
+    console.warn('[GeoGuessr Desktop] Failed to read stored scripts:', e);
+  }}
+}})();
+"#, payload = payload, window_key = window_key, sha256 = SHA256_JS)
+}
+
+// Synchronous SHA-256 for the bootstrap script, which can't wait for crypto.subtle
+const SHA256_JS: &str = r#"  // SHA-256 of a byte array. Synchronous, unlike crypto.subtle, so it can run before the page
+  var SHA256_K = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2
+  ];
+  function sha256(bytes) {
+    var padded = new Uint8Array(((bytes.length + 72) >> 6) << 6);
+    padded.set(bytes);
+    padded[bytes.length] = 0x80;
+    var view = new DataView(padded.buffer);
+    view.setUint32(padded.length - 8, Math.floor(bytes.length / 0x20000000));
+    view.setUint32(padded.length - 4, bytes.length << 3);
+
+    var hash = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+    var w = new Int32Array(64);
+    for (var offset = 0; offset < padded.length; offset += 64) {
+      for (var i = 0; i < 16; i++) w[i] = view.getInt32(offset + i * 4);
+      for (i = 16; i < 64; i++) {
+        var x = w[i - 15], y = w[i - 2];
+        var s0 = ((x >>> 7) | (x << 25)) ^ ((x >>> 18) | (x << 14)) ^ (x >>> 3);
+        var s1 = ((y >>> 17) | (y << 15)) ^ ((y >>> 19) | (y << 13)) ^ (y >>> 10);
+        w[i] = (w[i - 16] + s0 + w[i - 7] + s1) | 0;
+      }
+      var a = hash[0], b = hash[1], c = hash[2], d = hash[3], e = hash[4], f = hash[5], g = hash[6], h = hash[7];
+      for (i = 0; i < 64; i++) {
+        var t1 = (h + (((e >>> 6) | (e << 26)) ^ ((e >>> 11) | (e << 21)) ^ ((e >>> 25) | (e << 7)))
+          + ((e & f) ^ (~e & g)) + SHA256_K[i] + w[i]) | 0;
+        var t2 = ((((a >>> 2) | (a << 30)) ^ ((a >>> 13) | (a << 19)) ^ ((a >>> 22) | (a << 10)))
+          + ((a & b) ^ (a & c) ^ (b & c))) | 0;
+        h = g; g = f; f = e; e = (d + t1) | 0;
+        d = c; c = b; b = a; a = (t1 + t2) | 0;
+      }
+      hash[0] = (hash[0] + a) | 0; hash[1] = (hash[1] + b) | 0;
+      hash[2] = (hash[2] + c) | 0; hash[3] = (hash[3] + d) | 0;
+      hash[4] = (hash[4] + e) | 0; hash[5] = (hash[5] + f) | 0;
+      hash[6] = (hash[6] + g) | 0; hash[7] = (hash[7] + h) | 0;
+    }
+    var digest = new Uint8Array(32);
+    var digestView = new DataView(digest.buffer);
+    hash.forEach(function(word, i) { digestView.setInt32(i * 4, word); });
+    return digest;
+  }
+  function toHex(bytes) {
+    return Array.prototype.map.call(bytes, function(b) { return ('0' + b.toString(16)).slice(-2); }).join('');
+  }
+"#;
+
+// The loader run in a GeoGuessr page and the scripts it runs. The code has a
+// __GG_SCRIPT_TOKENS__ placeholder for those scripts' tokens, see get_bootstrap_script
+#[derive(Debug, Clone, Serialize)]
+struct InjectionScript {
+    code: String,
+    script_ids: Vec<String>,
+}
+
+fn get_initialization_script(state: &AppState, instance: &GameInstance) -> InjectionScript {
     use std::collections::HashSet;
-    use chrono::Utc;
 
     // Ensure GEF is loaded (core dependency for Discord presence)
     {
         let mut dependencies = state.dependencies.lock().unwrap();
         if !dependencies.contains_key(GEF_URL) {
             // Fetch GEF and cache it
-            match fetch_script_from_url(GEF_URL) {
-                Ok(gef_code) => {
-                    let dependency = ScriptDependency {
-                        url: GEF_URL.to_string(),
-                        code: gef_code,
-                        last_updated: Utc::now().timestamp() as u64,
-                    };
+            match fetch_dependency(GEF_URL) {
+                Ok(dependency) => {
                     dependencies.insert(GEF_URL.to_string(), dependency);
                     let _ = state.save_dependencies(&dependencies);
                     println!("[GeoGuessr Desktop] GEF loaded and cached");
@@ -578,10 +3016,11 @@ fn get_initialization_script(state: &AppState) -> String {
 
     let scripts = state.scripts.lock().unwrap();
     let dependencies = state.dependencies.lock().unwrap();
-    let mut enabled_scripts: Vec<_> = scripts.iter().filter(|s| s.enabled).collect();
+    let mut enabled_scripts: Vec<_> = scripts.iter().filter(|s| instance.runs_script(s)).collect();
 
     // Sort scripts by order (lower numbers load first)
     enabled_scripts.sort_by_key(|s| s.order);
+    let script_ids = enabled_scripts.iter().map(|script| script.id.clone()).collect();
 
     // Build JSON list of all scripts for settings panel
     let all_scripts_json = serde_json::to_string(&*scripts).unwrap_or_else(|_| "[]".to_string());
@@ -603,6 +3042,17 @@ fn get_initialization_script(state: &AppState) -> String {
     combined.push_str("  \n");
     combined.push_str("  console.log('[GeoGuessr Desktop] Initializing userscripts...');\n\n");
 
+    // Filled in by the bootstrap script. Claim keys are made anew on every load
+    combined.push_str("  // Tokens the scripts' APIs identify themselves to Rust with, and one-time keys\n");
+    combined.push_str("  // each script's wrapper claims its API with\n");
+    combined.push_str("  var scriptTokens = __GG_SCRIPT_TOKENS__;\n");
+    combined.push_str("  var claimKeys = {};\n");
+    combined.push_str("  Object.keys(scriptTokens).forEach(function(id) {\n");
+    combined.push_str("    claimKeys[id] = Array.prototype.map.call(crypto.getRandomValues(new Uint8Array(16)), function(b) {\n");
+    combined.push_str("      return ('0' + b.toString(16)).slice(-2);\n");
+    combined.push_str("    }).join('');\n");
+    combined.push_str("  });\n\n");
+
     // Base64 decode helper - this runs in isolated context
     combined.push_str("  function decodeBase64(str) {\n");
     combined.push_str("    return decodeURIComponent(atob(str).split('').map(function(c) {\n");
@@ -612,15 +3062,24 @@ fn get_initialization_script(state: &AppState) -> String {
 
     // Inject and run script in page's main world via script tag
     // This MUST use script tags to escape the isolated context
+    combined.push_str("  // DOM functions taken before the page's own code runs. Userscripts injected later (document-idle,\n");
+    combined.push_str("  // or after an SPA navigation) carry their claim key, a page that patched these must not get it\n");
+    combined.push_str("  var reflectApply = Reflect.apply;\n");
+    combined.push_str("  var createElement = Document.prototype.createElement;\n");
+    combined.push_str("  var getDocumentElement = Object.getOwnPropertyDescriptor(Document.prototype, 'documentElement').get;\n");
+    combined.push_str("  var setTextContent = Object.getOwnPropertyDescriptor(Node.prototype, 'textContent').set;\n");
+    combined.push_str("  var setAttribute = Element.prototype.setAttribute;\n");
+    combined.push_str("  var appendChild = Node.prototype.appendChild;\n");
+    combined.push_str("  var removeElement = Element.prototype.remove;\n");
     combined.push_str("  function injectIntoPage(code, name) {\n");
     combined.push_str("    console.log('[GeoGuessr Desktop] Injecting into page:', name);\n");
-    combined.push_str("    var script = document.createElement('script');\n");
-    combined.push_str("    script.textContent = code;\n");
-    combined.push_str("    script.setAttribute('data-geoguessr-desktop', name || 'userscript');\n");
+    combined.push_str("    var script = reflectApply(createElement, document, ['script']);\n");
+    combined.push_str("    reflectApply(setTextContent, script, [code]);\n");
+    combined.push_str("    reflectApply(setAttribute, script, ['data-geoguessr-desktop', name || 'userscript']);\n");
     combined.push_str("    // Inject into documentElement (exists before head/body)\n");
-    combined.push_str("    document.documentElement.appendChild(script);\n");
+    combined.push_str("    reflectApply(appendChild, reflectApply(getDocumentElement, document, []), [script]);\n");
     combined.push_str("    // Clean up immediately after execution\n");
-    combined.push_str("    script.remove();\n");
+    combined.push_str("    reflectApply(removeElement, script, []);\n");
     combined.push_str("  }\n\n");
 
     // Wait for documentElement to exist, then inject all scripts
@@ -638,111 +3097,811 @@ fn get_initialization_script(state: &AppState) -> String {
     combined.push_str("    }\n");
     combined.push_str("  }\n\n");
 
-    combined.push_str("  waitForDocumentElement(function() {\n");
-    combined.push_str("    console.log('[GeoGuessr Desktop] Document element ready, injecting scripts...');\n\n");
+    // Userscripts are queued with their URL rules and only injected on matching pages.
+    // Rules are re-checked on SPA navigations so e.g. /duels scripts start when entering a duel
+    combined.push_str("  var queuedUserscripts = [];\n");
+    combined.push_str("  function testUrlRules(sources, url) {\n");
+    combined.push_str("    return sources.some(function(source) {\n");
+    combined.push_str("      try {\n");
+    combined.push_str("        return new RegExp(source, 'i').test(url);\n");
+    combined.push_str("      } catch(e) {\n");
+    combined.push_str("        console.warn('[GeoGuessr Desktop] Invalid URL rule:', source);\n");
+    combined.push_str("        return false;\n");
+    combined.push_str("      }\n");
+    combined.push_str("    });\n");
+    combined.push_str("  }\n");
+    combined.push_str("  function urlMatchesRules(rules, url) {\n");
+    combined.push_str("    if (testUrlRules(rules.exclude, url)) return false;\n");
+    combined.push_str("    return rules.include.length === 0 || testUrlRules(rules.include, url);\n");
+    combined.push_str("  }\n");
+    combined.push_str("  function queueUserscript(name, rules, code) {\n");
+    combined.push_str("    queuedUserscripts.push({ name: name, rules: rules, code: code, injected: false });\n");
+    combined.push_str("  }\n");
+    combined.push_str("  function injectMatchingUserscripts() {\n");
+    combined.push_str("    var url = window.location.href;\n");
+    combined.push_str("    // A plain loop, the page may have replaced Array.prototype.forEach by now\n");
+    combined.push_str("    for (var i = 0; i < queuedUserscripts.length; i++) {\n");
+    combined.push_str("      var entry = queuedUserscripts[i];\n");
+    combined.push_str("      if (entry.injected || !urlMatchesRules(entry.rules, url)) continue;\n");
+    combined.push_str("      entry.injected = true;\n");
+    combined.push_str("      injectIntoPage(entry.code, entry.name);\n");
+    combined.push_str("    }\n");
+    combined.push_str("  }\n");
+    combined.push_str("  window.addEventListener('gg_locationchange', injectMatchingUserscripts);\n");
+    combined.push_str("  window.addEventListener('popstate', injectMatchingUserscripts);\n\n");
+
+    combined.push_str("  waitForDocumentElement(function() {\n");
+    combined.push_str("    console.log('[GeoGuessr Desktop] Document element ready, injecting scripts...');\n\n");
+
+    // Tampermonkey API - encode as base64
+    // Scripts don't see GM functions as page globals, each one gets its own API object
+    // holding only what it was granted (see __ggClaimGMApi in the userscript wrapper)
+    let tampermonkey_api = r#"(function(apiKeys, scriptApis) {
+  // Tauri's invoke, Channel and events, taken before the page's own code runs so it can't swap
+  // them. Script tokens and value changes go straight between Rust and here, never through
+  // window messages or DOM events the page could send too
+  var tauriCore = window.__TAURI__ && window.__TAURI__.core;
+  var tauriInvoke = tauriCore && tauriCore.invoke;
+  var TauriChannel = tauriCore && tauriCore.Channel;
+  var tauriEvent = window.__TAURI__ && window.__TAURI__.event;
+  var tauriWindow = window.__TAURI__ && window.__TAURI__.window;
+  function ggInvoke(command, args) {
+    if (!tauriInvoke) return Promise.reject('Tauri API not available');
+    return tauriInvoke(command, args || {});
+  }
+  // Per-script value storage backed by values.json in the data dir.
+  // Values are cached in the page so GM_getValue stays synchronous, writes go through to Rust
+  var valueStores = {};
+  function createValueStore(scriptId, scriptToken) {
+    var cache = {};
+    var written = {};
+    var loaded = false;
+    var waiting = [];
+    var listeners = {};
+    var nextListenerId = 1;
+
+    function notifyListeners(key, oldValue, newValue, remote) {
+      Object.keys(listeners).forEach(function(id) {
+        var listener = listeners[id];
+        if (listener.key !== key) return;
+        try {
+          listener.callback(key, oldValue, newValue, remote);
+        } catch(e) {
+          console.error('[GM_addValueChangeListener] Error in listener:', e);
+        }
+      });
+    }
+
+    // Values aren't part of the injected code, the script starts once they're loaded
+    // so GM_getValue can stay synchronous (see whenLoaded)
+    ggInvoke('gm_get_values', { scriptToken: scriptToken }).then(function(values) {
+      Object.keys(values || {}).forEach(function(key) {
+        if (!written[key]) cache[key] = values[key];
+      });
+    }).catch(function(e) {
+      console.warn('[GM_getValue] Failed to load values:', e);
+    }).then(function() {
+      loaded = true;
+      waiting.splice(0).forEach(function(callback) { callback(); });
+    });
+
+    function whenLoaded(callback) {
+      if (loaded) {
+        callback();
+      } else {
+        waiting.push(callback);
+      }
+    }
+
+    function setValue(key, value) {
+      var stored = value === undefined ? null : JSON.parse(JSON.stringify(value));
+      var oldValue = cache[key];
+      cache[key] = stored;
+      written[key] = true;
+      notifyListeners(key, oldValue, stored, false);
+      ggInvoke('gm_set_value', { scriptToken: scriptToken, key: key, value: stored }).catch(function(e) {
+        console.warn('[GM_setValue] Error:', e);
+      });
+    }
+
+    function getValue(key, defaultValue) {
+      if (Object.prototype.hasOwnProperty.call(cache, key)) return cache[key];
+      if (written[key]) return defaultValue;
+      // Adopt values saved by the old localStorage-backed shim
+      try {
+        var legacy = localStorage.getItem('gm_' + key);
+        if (legacy !== null) {
+          setValue(key, JSON.parse(legacy));
+          return cache[key];
+        }
+      } catch(e) {
+        console.warn('[GM_getValue] Error:', e);
+      }
+      return defaultValue;
+    }
+
+    function deleteValue(key) {
+      var oldValue = cache[key];
+      delete cache[key];
+      written[key] = true;
+      notifyListeners(key, oldValue, undefined, false);
+      ggInvoke('gm_delete_value', { scriptToken: scriptToken, key: key }).catch(function(e) {
+        console.warn('[GM_deleteValue] Error:', e);
+      });
+    }
+
+    function listValues() {
+      return Object.keys(cache);
+    }
+
+    function addValueChangeListener(key, callback) {
+      var id = nextListenerId++;
+      listeners[id] = { key: key, callback: callback };
+      return id;
+    }
+
+    function removeValueChangeListener(id) {
+      delete listeners[id];
+    }
+
+    // Change made by the same script in another window, the event only names the key
+    function applyRemoteChange(key) {
+      ggInvoke('gm_get_values', { scriptToken: scriptToken }).then(function(values) {
+        var oldValue = cache[key];
+        var deleted = !Object.prototype.hasOwnProperty.call(values || {}, key);
+        if (deleted) {
+          delete cache[key];
+        } else {
+          cache[key] = values[key];
+        }
+        written[key] = true;
+        notifyListeners(key, oldValue, cache[key], true);
+      }).catch(function(e) {
+        console.warn('[GM_addValueChangeListener] Failed to load changed value:', e);
+      });
+    }
+
+    valueStores[scriptId] = { applyRemoteChange: applyRemoteChange };
+
+    return {
+      getValue: getValue,
+      setValue: setValue,
+      deleteValue: deleteValue,
+      listValues: listValues,
+      addValueChangeListener: addValueChangeListener,
+      removeValueChangeListener: removeValueChangeListener,
+      whenLoaded: whenLoaded
+    };
+  }
+  // Value changes made by scripts in other windows, see gm_set_value. Changes from this window
+  // were already applied by the page itself
+  if (tauriEvent && tauriWindow) {
+    var currentLabel = tauriWindow.getCurrentWindow().label;
+    tauriEvent.listen('gm_value_changed', function(event) {
+      var change = event.payload;
+      if (!change || change.origin === currentLabel || !valueStores[change.script_id]) return;
+      valueStores[change.script_id].applyRemoteChange(change.key);
+    });
+  }
+  function addStyle(css) {
+    var style = document.createElement('style');
+    style.textContent = css;
+    (document.head || document.documentElement).appendChild(style);
+    return style;
+  }
+  // Encode request bodies for the bridge: strings are sent as-is, binary data as base64
+  function encodeRequestBody(data, callback) {
+    if (data === undefined || data === null) return callback({ data: null });
+    if (typeof data === 'string') return callback({ data: data });
+    if (data instanceof URLSearchParams) return callback({ data: data.toString() });
+    if (data instanceof Blob) {
+      data.arrayBuffer().then(function(buffer) {
+        encodeRequestBody(buffer, callback);
+      });
+      return;
+    }
+    if (data instanceof ArrayBuffer || ArrayBuffer.isView(data)) {
+      var bytes = data instanceof ArrayBuffer ? new Uint8Array(data) : new Uint8Array(data.buffer, data.byteOffset, data.byteLength);
+      var binary = '';
+      for (var i = 0; i < bytes.length; i += 0x8000) {
+        binary += String.fromCharCode.apply(null, bytes.subarray(i, i + 0x8000));
+      }
+      return callback({ dataBase64: btoa(binary) });
+    }
+    callback({ data: String(data) });
+  }
+  function decodeBase64Bytes(str) {
+    var binary = atob(str);
+    var bytes = new Uint8Array(binary.length);
+    for (var i = 0; i < binary.length; i++) bytes[i] = binary.charCodeAt(i);
+    return bytes;
+  }
+  function xmlhttpRequest(scriptToken, details) {
+    // Assigned by Rust, see the started event
+    var requestId = null;
+    var abortRequested = false;
+    var responseType = (details.responseType || '').toLowerCase();
+    var chunks = [];
+    var finished = false;
+    var state = {
+      readyState: 1,
+      status: 0,
+      statusText: '',
+      responseHeaders: '',
+      finalUrl: details.url,
+      loaded: 0,
+      total: 0
+    };
+
+    function responseBytes() {
+      var bytes = new Uint8Array(state.loaded);
+      var offset = 0;
+      chunks.forEach(function(chunk) {
+        bytes.set(chunk, offset);
+        offset += chunk.length;
+      });
+      return bytes;
+    }
+
+    function contentType() {
+      var match = /^content-type:\s*(.*)$/im.exec(state.responseHeaders);
+      return match ? match[1].trim() : '';
+    }
+
+    function buildResponse(withBody) {
+      var response = {
+        readyState: state.readyState,
+        status: state.status,
+        statusText: state.statusText,
+        responseHeaders: state.responseHeaders,
+        finalUrl: state.finalUrl,
+        loaded: state.loaded,
+        total: state.total,
+        lengthComputable: state.total > 0,
+        context: details.context
+      };
+      if (!withBody) return response;
+
+      var bytes = responseBytes();
+      if (responseType === 'arraybuffer') {
+        response.response = bytes.buffer;
+      } else if (responseType === 'blob') {
+        response.response = new Blob([bytes], { type: contentType() });
+      } else {
+        var text = new TextDecoder().decode(bytes);
+        response.responseText = text;
+        if (responseType === 'json') {
+          try {
+            response.response = JSON.parse(text);
+          } catch(e) {
+            response.response = undefined;
+          }
+        } else if (responseType === 'document') {
+          response.response = new DOMParser().parseFromString(text, contentType().split(';')[0] || 'text/html');
+        } else {
+          response.response = text;
+        }
+      }
+      return response;
+    }
+
+    function callback(name, response) {
+      if (typeof details[name] !== 'function') return;
+      try {
+        details[name](response);
+      } catch(e) {
+        console.error('[GM_xmlhttpRequest] Error in ' + name + ':', e);
+      }
+    }
+
+    function finish(name, response) {
+      if (finished) return;
+      finished = true;
+      state.readyState = 4;
+      callback(name, response);
+      callback('onloadend', response);
+    }
+
+    // Events streamed back from the Rust request over a channel
+    var eventHandler = function(detail) {
+      if (!detail || finished) return;
+
+      if (detail.event === 'started') {
+        requestId = detail.request_id;
+        if (abortRequested) sendAbort();
+      } else if (detail.event === 'headers') {
+        state.readyState = 2;
+        state.status = detail.status;
+        state.statusText = detail.status_text;
+        state.responseHeaders = detail.response_headers;
+        state.finalUrl = detail.final_url;
+        state.total = detail.total || 0;
+        callback('onreadystatechange', buildResponse(false));
+      } else if (detail.event === 'progress') {
+        chunks.push(decodeBase64Bytes(detail.data));
+        state.loaded = detail.loaded;
+        if (state.readyState !== 3) {
+          state.readyState = 3;
+          callback('onreadystatechange', buildResponse(false));
+        }
+        callback('onprogress', buildResponse(false));
+      } else if (detail.event === 'load') {
+        state.readyState = 4;
+        var response = buildResponse(true);
+        callback('onreadystatechange', response);
+        finish('onload', response);
+      } else if (detail.event === 'timeout') {
+        finish('ontimeout', buildResponse(false));
+      } else if (detail.event === 'abort') {
+        finish('onabort', buildResponse(false));
+      } else if (detail.event === 'error') {
+        console.error('[GM_xmlhttpRequest] Error:', detail.error);
+        var errorResponse = buildResponse(false);
+        errorResponse.error = detail.error;
+        finish('onerror', errorResponse);
+      }
+    };
+
+    callback('onloadstart', buildResponse(false));
+
+    // Headers, body chunks and the final outcome are streamed over a channel
+    encodeRequestBody(details.data, function(body) {
+      if (finished) return;
+      if (abortRequested) return finish('onabort', buildResponse(false));
+      if (!TauriChannel) return eventHandler({ event: 'error', error: 'Tauri API not available' });
+      var channel = new TauriChannel();
+      channel.onmessage = eventHandler;
+      ggInvoke('gm_xhr', {
+        request: {
+          script_token: scriptToken,
+          url: details.url,
+          method: details.method || 'GET',
+          headers: details.headers || null,
+          data: body.data || null,
+          data_base64: body.dataBase64 || null,
+          timeout: details.timeout || null
+        },
+        onEvent: channel
+      }).catch(function(error) {
+        eventHandler({ event: 'error', error: error.toString() });
+      });
+    });
+
+    function sendAbort() {
+      ggInvoke('gm_xhr_abort', { requestId: requestId }).catch(function(e) {
+        console.error('[GM_xmlhttpRequest] Abort failed:', e);
+      });
+    }
+
+    return {
+      abort: function() {
+        if (finished || abortRequested) return;
+        abortRequested = true;
+        // Before the started event there is no id yet, it's sent once that arrives
+        if (requestId) sendAbort();
+      }
+    };
+  }
+  // GM_openInTab - opens URL in default browser
+  function openInTab(url, options) {
+    window.dispatchEvent(new CustomEvent('gm_open_external', { detail: { url: url } }));
+  }
+  // GM_notification - native desktop notification, onclick/ondone come back over a channel
+  function notification(scriptToken, details, ondone) {
+    if (typeof details !== 'object' || details === null) {
+      // Legacy signature: GM_notification(text, title, image, onclick)
+      details = { text: details, title: arguments[2], image: arguments[3], onclick: arguments[4] };
+      ondone = undefined;
+    }
+    var done = typeof ondone === 'function' ? ondone : details.ondone;
+
+    var eventHandler = function(detail) {
+      if (!detail) return;
+      if (detail.error) console.error('[GM_notification] Error:', detail.error);
+      try {
+        if (detail.event === 'click' && typeof details.onclick === 'function') details.onclick();
+        if (detail.event === 'done' && typeof done === 'function') done();
+      } catch(e) {
+        console.error('[GM_notification] Error in callback:', e);
+      }
+    };
+
+    if (!TauriChannel) return eventHandler({ event: 'done', error: 'Tauri API not available' });
+    var channel = new TauriChannel();
+    channel.onmessage = eventHandler;
+    ggInvoke('gm_notification', {
+      notification: {
+        script_token: scriptToken,
+        title: details.title ? String(details.title) : null,
+        text: String(details.text || ''),
+        image: details.image ? String(details.image) : null,
+        timeout: typeof details.timeout === 'number' ? details.timeout : null
+      },
+      onEvent: channel
+    }).catch(function(error) {
+      eventHandler({ event: 'done', error: error.toString() });
+    });
+  }
+  // GM_setClipboard - info is 'text', 'html' or { type, mimetype }
+  function setClipboard(scriptToken, data, info) {
+    var type = info && typeof info === 'object' ? (info.mimetype || info.type) : info;
+    var mimeType = type === 'html' || type === 'text/html' ? 'text/html' : 'text/plain';
+    return ggInvoke('gm_set_clipboard', { scriptToken: scriptToken, data: String(data), mimeType: mimeType });
+  }
+  // GM_download - saved by the app into the configured download folder. blob: and data: URLs
+  // only exist in the page, so their content is read here and sent along
+  function download(scriptToken, details, name) {
+    if (typeof details !== 'object' || details === null) details = { url: details, name: name };
+    var downloadId = 'gm_download_' + Date.now() + '_' + Math.random().toString(36).substr(2, 9);
+    var finished = false;
+
+    function callback(name, response) {
+      if (typeof details[name] !== 'function') return;
+      try {
+        details[name](response);
+      } catch(e) {
+        console.error('[GM_download] Error in ' + name + ' callback:', e);
+      }
+    }
+
+    function finish(name, response) {
+      if (finished) return;
+      finished = true;
+      callback(name, response);
+    }
+
+    var eventHandler = function(detail) {
+      if (!detail) return;
+      if (detail.event === 'progress') {
+        callback('onprogress', {
+          loaded: detail.loaded,
+          total: detail.total || 0,
+          lengthComputable: detail.total !== null && detail.total !== undefined
+        });
+      } else if (detail.event === 'load') {
+        finish('onload', { path: detail.path });
+      } else if (detail.event === 'timeout') {
+        finish('ontimeout', { error: 'timeout' });
+      } else if (detail.event === 'abort') {
+        finish('onabort', { error: 'aborted' });
+      } else if (detail.event === 'error') {
+        console.error('[GM_download] Error:', detail.error);
+        finish('onerror', { error: 'not_succeeded', details: detail.error });
+      }
+    };
+
+    // Progress and the outcome are streamed back over a channel like for GM_xmlhttpRequest
+    function send(source) {
+      if (finished) return;
+      if (!TauriChannel) return eventHandler({ event: 'error', error: 'Tauri API not available' });
+      var channel = new TauriChannel();
+      channel.onmessage = eventHandler;
+      ggInvoke('gm_download', {
+        request: {
+          download_id: downloadId,
+          script_token: scriptToken,
+          url: source.url || null,
+          data_base64: source.dataBase64 !== undefined ? source.dataBase64 : null,
+          name: details.name ? String(details.name) : null,
+          headers: details.headers || null,
+          save_as: details.saveAs === true,
+          conflict_action: details.conflictAction || null,
+          timeout: details.timeout || null
+        },
+        onEvent: channel
+      }).catch(function(error) {
+        eventHandler({ event: 'error', error: error.toString() });
+      });
+    }
+
+    var url = details.url;
+    if (url instanceof Blob) {
+      encodeRequestBody(url, send);
+    } else if (typeof url === 'string' && /^(blob|data):/i.test(url)) {
+      fetch(url)
+        .then(function(response) { return response.blob(); })
+        .then(function(blob) { encodeRequestBody(blob, send); })
+        .catch(function(e) { finish('onerror', { error: 'not_succeeded', details: e.toString() }); });
+    } else {
+      send({ url: url ? String(url) : null });
+    }
+
+    return {
+      abort: function() {
+        if (finished) return;
+        ggInvoke('gm_download_abort', { downloadId: downloadId }).catch(function(e) {
+          console.error('[GM_download] Abort failed:', e);
+        });
+        finish('onabort', { error: 'aborted' });
+      }
+    };
+  }
+  var VALUE_APIS = ['getValue', 'setValue', 'deleteValue', 'listValues',
+    'addValueChangeListener', 'removeValueChangeListener'];
+  // Promise-based GM.xmlHttpRequest, resolving on load and rejecting on error/timeout/abort
+  function xmlHttpRequestAsync(scriptToken, details) {
+    var handle;
+    var promise = new Promise(function(resolve, reject) {
+      function settle(name, settleWith) {
+        return function(response) {
+          if (typeof details[name] === 'function') details[name](response);
+          settleWith(response);
+        };
+      }
+      handle = xmlhttpRequest(scriptToken, Object.assign({}, details, {
+        onload: settle('onload', resolve),
+        onerror: settle('onerror', reject),
+        ontimeout: settle('ontimeout', reject),
+        onabort: settle('onabort', reject)
+      }));
+    });
+    promise.abort = function() { handle.abort(); };
+    return promise;
+  }
+  // Promise-based GM.download, resolving on load and rejecting on error/timeout/abort
+  function downloadAsync(scriptToken, details, name) {
+    if (typeof details !== 'object' || details === null) details = { url: details, name: name };
+    var handle;
+    var promise = new Promise(function(resolve, reject) {
+      function settle(name, settleWith) {
+        return function(response) {
+          if (typeof details[name] === 'function') details[name](response);
+          settleWith(response);
+        };
+      }
+      handle = download(scriptToken, Object.assign({}, details, {
+        onload: settle('onload', resolve),
+        onerror: settle('onerror', reject),
+        ontimeout: settle('ontimeout', reject),
+        onabort: settle('onabort', reject)
+      }));
+    });
+    promise.abort = function() { handle.abort(); };
+    return promise;
+  }
+  // @resource access. Content comes from Rust's cache once, when the script's API is claimed,
+  // and the script starts after that so GM_getResourceText can stay synchronous
+  function createResourceStore(scriptToken, names) {
+    var resources = {};
+    var blobUrls = {};
+    var pending = names.length;
+    var waiting = [];
+
+    names.forEach(function(name) {
+      ggInvoke('gm_get_resource', { scriptToken: scriptToken, name: name }).then(function(resource) {
+        if (resource) resources[name] = resource;
+      }).catch(function(e) {
+        console.warn('[GM_getResourceText] Failed to load resource ' + name + ':', e);
+      }).then(function() {
+        pending--;
+        if (pending === 0) waiting.splice(0).forEach(function(callback) { callback(); });
+      });
+    });
+
+    function whenLoaded(callback) {
+      if (pending === 0) {
+        callback();
+      } else {
+        waiting.push(callback);
+      }
+    }
 
-    // Tampermonkey API - encode as base64
-    // Put everything on window so it's accessible across all script injections
-    let tampermonkey_api = r#"
-window.unsafeWindow = window;
-window.GM_info = {
-  script: { name: 'GeoGuessr Desktop', version: '1.0' },
-  scriptHandler: 'GeoGuessr Desktop',
-  version: '1.0'
-};
-window.GM_getValue = function(key, defaultValue) {
-  try {
-    var value = localStorage.getItem('gm_' + key);
-    return value !== null ? JSON.parse(value) : defaultValue;
-  } catch(e) {
-    console.warn('[GM_getValue] Error:', e);
-    return defaultValue;
-  }
-};
-window.GM_setValue = function(key, value) {
-  try {
-    localStorage.setItem('gm_' + key, JSON.stringify(value));
-  } catch(e) {
-    console.warn('[GM_setValue] Error:', e);
+    function getResourceText(name) {
+      var resource = resources[name];
+      if (!resource) return null;
+      return new TextDecoder().decode(decodeBase64Bytes(resource.data));
+    }
+
+    // Data URLs by default since they work in CSS and <img> everywhere, blob URLs on request
+    function getResourceURL(name, isBlobUrl) {
+      var resource = resources[name];
+      if (!resource) return null;
+      if (isBlobUrl !== true) return 'data:' + resource.mimeType + ';base64,' + resource.data;
+      if (!blobUrls[name]) {
+        blobUrls[name] = URL.createObjectURL(new Blob([decodeBase64Bytes(resource.data)], { type: resource.mimeType }));
+      }
+      return blobUrls[name];
+    }
+
+    return { getResourceText: getResourceText, getResourceURL: getResourceURL, whenLoaded: whenLoaded };
   }
-};
-window.GM_deleteValue = function(key) {
-  try {
-    localStorage.removeItem('gm_' + key);
-  } catch(e) {
-    console.warn('[GM_deleteValue] Error:', e);
+  // GM_registerMenuCommand - commands show up under the script in the settings panel
+  // and in the manager window, clicks come back as gg_menu_command_invoke messages
+  var menuCommands = {};
+  var nextMenuCommandId = 1;
+  function postMenuCommand(type, command) {
+    window.postMessage({ type: type, scriptId: command.scriptId, commandId: command.commandId, caption: command.caption }, '*');
   }
-};
-window.GM_listValues = function() {
-  var keys = [];
-  try {
-    for (var i = 0; i < localStorage.length; i++) {
-      var key = localStorage.key(i);
-      if (key.indexOf('gm_') === 0) keys.push(key.substring(3));
-    }
-  } catch(e) {
-    console.warn('[GM_listValues] Error:', e);
+  function createMenuCommands(scriptId, scriptToken) {
+    function registerMenuCommand(caption, callback, options) {
+      var id = options && typeof options === 'object' && options.id !== undefined ? options.id : nextMenuCommandId++;
+      var command = { scriptId: scriptId, commandId: String(id), caption: String(caption), callback: callback };
+      menuCommands[scriptId + ':' + command.commandId] = command;
+      postMenuCommand('gg_menu_command_register', command);
+      ggInvoke('gm_register_menu_command', { scriptToken: scriptToken, commandId: command.commandId, caption: command.caption })
+        .catch(function(e) { console.warn('[GM_registerMenuCommand] Error:', e); });
+      return id;
+    }
+
+    function unregisterMenuCommand(id) {
+      var command = menuCommands[scriptId + ':' + String(id)];
+      if (!command) return;
+      delete menuCommands[scriptId + ':' + command.commandId];
+      postMenuCommand('gg_menu_command_unregister', command);
+      ggInvoke('gm_unregister_menu_command', { scriptToken: scriptToken, commandId: command.commandId })
+        .catch(function(e) { console.warn('[GM_unregisterMenuCommand] Error:', e); });
+    }
+
+    return { registerMenuCommand: registerMenuCommand, unregisterMenuCommand: unregisterMenuCommand };
   }
-  return keys;
-};
-window.GM_addStyle = function(css) {
-  var style = document.createElement('style');
-  style.textContent = css;
-  (document.head || document.documentElement).appendChild(style);
-};
-window.GM_xmlhttpRequest = function(details) {
-  // Use custom event to communicate with isolated context which has Tauri access
-  var requestId = 'gm_xhr_' + Date.now() + '_' + Math.random().toString(36).substr(2, 9);
-
-  // Listen for response
-  var responseHandler = function(event) {
-    if (event.detail && event.detail.requestId === requestId) {
-      window.removeEventListener('gm_xhr_response', responseHandler);
-      if (event.detail.error) {
-        console.error('[GM_xmlhttpRequest] Error:', event.detail.error);
-        if (details.onerror) details.onerror(event.detail.error);
-      } else if (details.onload) {
-        details.onload({
-          responseText: event.detail.responseText,
-          status: event.detail.status,
-          statusText: event.detail.statusText,
-          responseHeaders: event.detail.responseHeaders
+  window.addEventListener('message', function(event) {
+    var data = event.data;
+    if (!data) return;
+    if (data.type === 'gg_menu_commands_request') {
+      Object.keys(menuCommands).forEach(function(key) {
+        postMenuCommand('gg_menu_command_register', menuCommands[key]);
+      });
+    } else if (data.type === 'gg_menu_command_invoke') {
+      var command = menuCommands[data.scriptId + ':' + data.commandId];
+      if (!command) return;
+      try {
+        command.callback();
+      } catch(e) {
+        console.error('[GM_registerMenuCommand] Error in command:', e);
+      }
+    }
+  });
+  // Build the API for one script, containing only what its @grant lines ask for, along with
+  // whenReady, which the wrapper starts the script with
+  function createGMApi(script) {
+    var granted = {};
+    script.grants.forEach(function(name) { granted[name] = true; });
+
+    var info = script.info;
+    var api = {
+      unsafeWindow: window,
+      GM_info: info,
+      GM: { info: info }
+    };
+
+    // GM_* callback style functions
+    var available = {
+      GM_addStyle: addStyle,
+      GM_xmlhttpRequest: function(details) { return xmlhttpRequest(script.token, details); },
+      GM_openInTab: openInTab,
+      GM_notification: function() {
+        return notification.apply(null, [script.token].concat(Array.prototype.slice.call(arguments)));
+      },
+      GM_setClipboard: function(data, info, callback) {
+        setClipboard(script.token, data, info)
+          .then(function() { if (typeof callback === 'function') callback(); })
+          .catch(function(e) { console.error('[GM_setClipboard] Error:', e); });
+      },
+      GM_download: function(details, name) { return download(script.token, details, name); }
+    };
+    // GM.* promise style equivalents (Greasemonkey 4)
+    var availableAsync = {
+      addStyle: function(css) { return Promise.resolve(addStyle(css)); },
+      xmlHttpRequest: function(details) { return xmlHttpRequestAsync(script.token, details); },
+      openInTab: function(url, options) { return Promise.resolve(openInTab(url, options)); },
+      setClipboard: function(data, info) { return setClipboard(script.token, data, info).then(function() {}); },
+      download: function(details, name) { return downloadAsync(script.token, details, name); },
+      // Resolves once the notification is gone, like Greasemonkey's GM.notification
+      notification: function(details, ondone) {
+        var args = arguments;
+        return new Promise(function(resolve) {
+          if (typeof details !== 'object' || details === null) {
+            details = { text: details, title: args[1], image: args[2], onclick: args[3] };
+          }
+          notification(script.token, Object.assign({}, details, {
+            ondone: function() {
+              var done = typeof ondone === 'function' ? ondone : details.ondone;
+              if (typeof done === 'function') done();
+              resolve();
+            }
+          }));
         });
       }
+    };
+
+    var resources = createResourceStore(script.token, script.resources);
+    available.GM_getResourceText = resources.getResourceText;
+    available.GM_getResourceURL = resources.getResourceURL;
+    availableAsync.getResourceText = function(name) { return Promise.resolve(resources.getResourceText(name)); };
+    availableAsync.getResourceUrl = function(name) { return Promise.resolve(resources.getResourceURL(name)); };
+
+    var menu = createMenuCommands(script.id, script.token);
+    available.GM_registerMenuCommand = menu.registerMenuCommand;
+    available.GM_unregisterMenuCommand = menu.unregisterMenuCommand;
+    availableAsync.registerMenuCommand = function(caption, callback, options) {
+      return Promise.resolve(menu.registerMenuCommand(caption, callback, options));
+    };
+    availableAsync.unregisterMenuCommand = function(id) {
+      return Promise.resolve(menu.unregisterMenuCommand(id));
+    };
+
+    var needsValues = VALUE_APIS.some(function(name) {
+      return granted['GM_' + name] || granted['GM.' + name];
+    });
+    var values = null;
+    if (needsValues) {
+      values = createValueStore(script.id, script.token);
+      VALUE_APIS.forEach(function(name) {
+        available['GM_' + name] = values[name];
+        availableAsync[name] = function() {
+          var args = arguments;
+          return new Promise(function(resolve) { resolve(values[name].apply(null, args)); });
+        };
+      });
+    }
+
+    Object.keys(available).forEach(function(name) {
+      if (granted[name]) api[name] = available[name];
+    });
+    Object.keys(availableAsync).forEach(function(name) {
+      if (granted['GM.' + name]) api.GM[name] = availableAsync[name];
+    });
+    function whenReady(callback) {
+      resources.whenLoaded(function() {
+        if (values) {
+          values.whenLoaded(callback);
+        } else {
+          callback();
+        }
+      });
     }
-  };
-  window.addEventListener('gm_xhr_response', responseHandler);
-
-  // Send request to isolated context
-  window.dispatchEvent(new CustomEvent('gm_xhr_request', {
-    detail: {
-      requestId: requestId,
-      url: details.url,
-      method: details.method || 'GET',
-      headers: details.headers || null,
-      data: details.data || null
-    }
-  }));
-};
-// GM_openInTab - opens URL in default browser
-window.GM_openInTab = function(url, options) {
-  window.dispatchEvent(new CustomEvent('gm_open_external', { detail: { url: url } }));
-};
-// Also create local references for scripts that expect them as globals
-var unsafeWindow = window.unsafeWindow;
-var GM_info = window.GM_info;
-var GM_getValue = window.GM_getValue;
-var GM_setValue = window.GM_setValue;
-var GM_deleteValue = window.GM_deleteValue;
-var GM_listValues = window.GM_listValues;
-var GM_addStyle = window.GM_addStyle;
-var GM_xmlhttpRequest = window.GM_xmlhttpRequest;
-var GM_openInTab = window.GM_openInTab;
-console.log('[GeoGuessr Desktop] Tampermonkey API compatibility loaded');
+    return { api: api, whenReady: whenReady };
+  }
+  // Each script's wrapper claims its API once, with the key only it was given, so page
+  // code can neither take a script's API nor build one with grants of its choosing.
+  // Non-enumerable so nothing GM-related shows up among the page's globals
+  var unclaimedApis = {};
+  scriptApis.forEach(function(script) {
+    script.token = apiKeys.tokens[script.id];
+    unclaimedApis[apiKeys.claims[script.id]] = script;
+  });
+  Object.defineProperty(window, '__ggClaimGMApi', {
+    value: function(token) {
+      var script = unclaimedApis[token];
+      if (!script) return null;
+      delete unclaimedApis[token];
+      return createGMApi(script);
+    }
+  });
+  console.log('[GeoGuessr Desktop] Tampermonkey API compatibility loaded');
+})(__GG_API_KEYS__, __GG_SCRIPT_APIS__);
 "#;
+    let script_apis: Vec<serde_json::Value> = enabled_scripts.iter()
+        .map(|script| serde_json::json!({
+            "id": script.id,
+            "grants": script.granted_apis(),
+            "info": script_info(script),
+            "resources": script_resource_names(script),
+        }))
+        .collect();
+    let tampermonkey_api = tampermonkey_api.replace("__GG_SCRIPT_APIS__", &serde_json::Value::Array(script_apis).to_string());
     let api_base64 = BASE64.encode(tampermonkey_api.as_bytes());
     // Inject Tampermonkey API into page's main world
-    combined.push_str(&format!("    injectIntoPage(decodeBase64('{}'), 'tampermonkey-api');\n\n", api_base64));
+    combined.push_str(&format!("    injectIntoPage(decodeBase64('{}').replace('__GG_API_KEYS__', function() {{\n", api_base64));
+    combined.push_str("      return JSON.stringify({ tokens: scriptTokens, claims: claimKeys });\n");
+    combined.push_str("    }), 'tampermonkey-api');\n\n");
+
+    // GeoGuessr navigates with the History API, which fires no event of its own.
+    // Wrap pushState/replaceState in the page so the loader can re-check URL rules
+    let navigation_hook = r#"(function() {
+  if (window.__ggNavigationHooked) return;
+  window.__ggNavigationHooked = true;
+  ['pushState', 'replaceState'].forEach(function(method) {
+    var original = history[method];
+    history[method] = function() {
+      var result = original.apply(this, arguments);
+      window.dispatchEvent(new Event('gg_locationchange'));
+      return result;
+    };
+  });
+})();"#;
+    let navigation_base64 = BASE64.encode(navigation_hook.as_bytes());
+    combined.push_str(&format!("    injectIntoPage(decodeBase64('{}'), 'navigation-hook');\n\n", navigation_base64));
 
     // Inject custom titlebar with settings panel
     let titlebar_code = format!(r#"(function() {{
@@ -808,6 +3967,7 @@ console.log('[GeoGuessr Desktop] Tampermonkey API compatibility loaded');
       <input type="text" id="gg-add-url" placeholder="Script URL (https://...)" />
       <button id="gg-add-btn">Add</button>
     </div>
+    <div class="gg-settings-hint">or drop a .user.js file onto the window</div>
     <div class="gg-settings-actions">
       <button id="gg-apply-btn" disabled>Apply &amp; Reload</button>
     </div>
@@ -991,6 +4151,29 @@ console.log('[GeoGuessr Desktop] Tampermonkey API compatibility loaded');
       font-size: 11px;
       margin-top: 2px;
     }}
+    .gg-script-commands {{
+      display: flex;
+      flex-wrap: wrap;
+      gap: 4px;
+      margin-top: 6px;
+    }}
+    .gg-script-commands:empty {{
+      display: none;
+    }}
+    .gg-script-command {{
+      padding: 2px 8px;
+      background: #252542;
+      border: 1px solid #3a3a5a;
+      border-radius: 4px;
+      color: #b0b0b0;
+      cursor: pointer;
+      font-size: 11px;
+      transition: all 0.15s;
+    }}
+    .gg-script-command:hover {{
+      background: #2a2a4a;
+      color: #fff;
+    }}
     .gg-script-refresh {{
       padding: 4px 8px;
       background: transparent;
@@ -1055,6 +4238,11 @@ console.log('[GeoGuessr Desktop] Tampermonkey API compatibility loaded');
     .gg-settings-add button:hover {{
       background: #5b4cdb;
     }}
+    .gg-settings-hint {{
+      padding: 0 16px 10px;
+      font-size: 11px;
+      color: #606080;
+    }}
     .gg-settings-actions {{
       padding: 12px 16px;
       border-top: 1px solid #2a2a4a;
@@ -1160,6 +4348,55 @@ console.log('[GeoGuessr Desktop] Tampermonkey API compatibility loaded');
     var scriptsData = {scripts_json};
     var pendingChanges = {{}};
     var hasChanges = false;
+    // GM_registerMenuCommand entries by script id, kept in sync by the Tampermonkey API
+    var menuCommands = {{}};
+
+    function escapeHtml(text) {{
+      var div = document.createElement('div');
+      div.textContent = text;
+      return div.innerHTML;
+    }}
+
+    function renderMenuCommands(scriptId) {{
+      return (menuCommands[scriptId] || []).map(function(command) {{
+        return '<button class="gg-script-command" data-script="' + scriptId + '" data-command="' +
+          escapeHtml(command.commandId) + '">' + escapeHtml(command.caption) + '</button>';
+      }}).join('');
+    }}
+
+    window.addEventListener('message', function(e) {{
+      var data = e.data;
+      if (!data || !data.scriptId) return;
+      if (data.type === 'gg_menu_command_register') {{
+        var commands = (menuCommands[data.scriptId] || []).filter(function(c) {{ return c.commandId !== data.commandId; }});
+        commands.push({{ commandId: data.commandId, caption: data.caption }});
+        menuCommands[data.scriptId] = commands;
+        renderScripts();
+      }} else if (data.type === 'gg_menu_command_unregister') {{
+        menuCommands[data.scriptId] = (menuCommands[data.scriptId] || []).filter(function(c) {{ return c.commandId !== data.commandId; }});
+        renderScripts();
+      }}
+    }});
+
+    // Userscripts dropped onto the window are imported by the app and reported by the bridge
+    window.addEventListener('message', function(e) {{
+      var data = e.data;
+      if (!data) return;
+      var statusEl = document.getElementById('gg-settings-status');
+      if (data.type === 'gg_script_imported') {{
+        scriptsData.push(data.script);
+        renderScripts();
+        hasChanges = true;
+        updateApplyButton();
+        document.getElementById('gg-settings-panel').style.display = 'block';
+        statusEl.textContent = 'Imported ' + data.script.name + '! Click Apply & Reload to activate.';
+        statusEl.className = 'success';
+      }} else if (data.type === 'gg_script_import_error') {{
+        document.getElementById('gg-settings-panel').style.display = 'block';
+        statusEl.textContent = 'Error: ' + data.error;
+        statusEl.className = 'error';
+      }}
+    }});
 
     // Update Apply button state
     function updateApplyButton() {{
@@ -1192,6 +4429,7 @@ console.log('[GeoGuessr Desktop] Tampermonkey API compatibility loaded');
             <div class="gg-script-info">
               <div class="gg-script-name">${{script.name}}</div>
               <div class="gg-script-meta">${{script.version || 'No version'}}${{script.author ? ' by ' + script.author : ''}}</div>
+              <div class="gg-script-commands">${{renderMenuCommands(script.id)}}</div>
             </div>
             ${{script.url ? '<button class="gg-script-refresh" data-id="' + script.id + '">↻</button>' : ''}}
             <button class="gg-script-delete" data-id="${{script.id}}">×</button>
@@ -1199,6 +4437,14 @@ console.log('[GeoGuessr Desktop] Tampermonkey API compatibility loaded');
         `;
       }}).join('');
 
+      // Add menu command handlers
+      list.querySelectorAll('.gg-script-command').forEach(function(btn) {{
+        btn.addEventListener('click', function() {{
+          window.postMessage({{ type: 'gg_menu_command_invoke', scriptId: this.dataset.script, commandId: this.dataset.command }}, '*');
+          document.getElementById('gg-settings-panel').style.display = 'none';
+        }});
+      }});
+
       // Add toggle handlers
       list.querySelectorAll('.gg-script-toggle').forEach(function(toggle) {{
         toggle.addEventListener('click', function() {{
@@ -1230,9 +4476,10 @@ console.log('[GeoGuessr Desktop] Tampermonkey API compatibility loaded');
                 var idx = scriptsData.findIndex(function(s) {{ return s.id === id; }});
                 if (idx !== -1) scriptsData[idx] = e.data.result;
                 renderScripts();
-                hasChanges = true;
-                updateApplyButton();
-                statusEl.textContent = 'Script refreshed! Click Apply & Reload to use.';
+                // New versions only run once they're accepted in the script manager
+                statusEl.textContent = e.data.result.pending_update
+                  ? 'Update found! Review and accept it in the script manager.'
+                  : 'Script is up to date.';
                 statusEl.className = 'success';
               }}
             }}
@@ -1276,6 +4523,9 @@ console.log('[GeoGuessr Desktop] Tampermonkey API compatibility loaded');
 
     renderScripts();
 
+    // Scripts running at document-start may have registered menu commands before the titlebar existed
+    window.postMessage({{ type: 'gg_menu_commands_request' }}, '*');
+
     // Settings panel toggle
     document.getElementById('gg-settings-btn').addEventListener('click', function(e) {{
       e.stopPropagation();
@@ -1373,7 +4623,7 @@ console.log('[GeoGuessr Desktop] Tampermonkey API compatibility loaded');
       var hasError = false;
 
       function reloadWindow() {{
-        // Use reload_scripts command to close and reopen window with fresh init script
+        // Use reload_scripts command to reload the windows with the current scripts
         var requestId = 'req_reload_' + Date.now();
         window.postMessage({{ type: 'gg_invoke', requestId: requestId, command: 'reload_scripts', args: {{}} }}, '*');
       }}
@@ -1622,6 +4872,7 @@ console.log('[GeoGuessr Desktop] Tampermonkey API compatibility loaded');
     if !non_gef_requires.is_empty() {
         combined.push_str("    // === Injecting userscript dependencies ===\n");
         for (dep_index, req_url) in non_gef_requires.iter().enumerate() {
+            // Only content that passed the URL's integrity hashes is ever cached
             if let Some(dep) = dependencies.get(*req_url) {
                 combined.push_str(&format!("    console.log('[GeoGuessr Desktop] Loading dependency: {}');\n", req_url));
                 // Use base64 encoding to avoid escaping issues
@@ -1641,8 +4892,48 @@ console.log('[GeoGuessr Desktop] Tampermonkey API compatibility loaded');
     for script in enabled_scripts {
         combined.push_str(&format!("    console.log('[GeoGuessr Desktop] Queuing script: {}');\n", script.name));
 
-        // Wrap the script to run on load, then encode as base64
+        // Schedule the script at its @run-at phase (document-idle waits for load, as before)
+        let schedule = match script.run_at.as_deref() {
+            Some("document-start") => "  runScript();",
+            // Watched with a MutationObserver, animation frames don't run in hidden windows
+            Some("document-body") => r#"  if (document.body) {
+    runScript();
+  } else {
+    var bodyObserver = new MutationObserver(function() {
+      if (document.body) {
+        bodyObserver.disconnect();
+        runScript();
+      }
+    });
+    bodyObserver.observe(document, { childList: true, subtree: true });
+  }"#,
+            Some("document-end") => r#"  if (document.readyState === 'loading') {
+    document.addEventListener('DOMContentLoaded', runScript);
+  } else {
+    runScript();
+  }"#,
+            _ => r#"  if (document.readyState === 'complete') {
+    runScript();
+  } else {
+    window.addEventListener('load', runScript);
+  }"#,
+        };
+
+        let grants = script.granted_apis();
+        // GM.* functions live on the GM object, only GM_* ones need their own variable
+        let script_api_locals = ["unsafeWindow", "GM_info", "GM"].iter()
+            .chain(grants.iter().filter(|name| name.starts_with("GM_")))
+            .map(|name| format!("  var {} = __ggApi.{};", name, name))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        // Wrap the script with error handling, then encode as base64
         let wrapped_script = format!(r#"(function() {{
+  // GM functions are scoped to this script, only granted ones are declared
+  var __ggClaim = window.__ggClaimGMApi('__GG_CLAIM_KEY__');
+  if (!__ggClaim) return;
+  var __ggApi = __ggClaim.api;
+{}
   var runScript = function() {{
     try {{
       console.log('[GeoGuessr Desktop] Executing script: {}');
@@ -1652,67 +4943,50 @@ console.log('[GeoGuessr Desktop] Tampermonkey API compatibility loaded');
       console.error('[GeoGuessr Desktop] Error in script {}: ', e);
     }}
   }};
-  if (document.readyState === 'complete') {{
-    runScript();
-  }} else {{
-    window.addEventListener('load', runScript);
-  }}
-}})();"#, script.name, script.code, script.name, script.name);
+  // Scripts reading @resource files start once those are loaded
+  __ggClaim.whenReady(function() {{
+{}
+  }});
+}})();"#, script_api_locals, script.name, script.code, script.name, script.name, schedule);
 
         let script_base64 = BASE64.encode(wrapped_script.as_bytes());
-        // Inject into page's main world
-        combined.push_str(&format!("    injectIntoPage(decodeBase64('{}'), '{}');\n\n",
-            script_base64, script.name));
+        let script_name_json = serde_json::to_string(&script.name).unwrap_or_else(|_| "'userscript'".to_string());
+        // Queue for injection into page's main world on matching URLs
+        let script_id_json = serde_json::to_string(&script.id).unwrap_or_default();
+        // The wrapper gets its claim key on the way in, see __ggClaimGMApi
+        combined.push_str(&format!("    queueUserscript({}, {}, decodeBase64('{}').replace('__GG_CLAIM_KEY__', claimKeys[{}]));\n\n",
+            script_name_json, script_url_rules(script), script_base64, script_id_json));
     }
+    combined.push_str("    injectMatchingUserscripts();\n");
 
     // Close the waitForDocumentElement callback
     combined.push_str("  });\n");
 
-    // Set up GM_xmlhttpRequest bridge in isolated context (has Tauri access)
-    combined.push_str("\n  // GM_xmlhttpRequest bridge - listens for requests from page and uses Tauri\n");
-    combined.push_str("  window.addEventListener('gm_xhr_request', function(event) {\n");
-    combined.push_str("    var detail = event.detail;\n");
-    combined.push_str("    if (!detail || !detail.requestId || !detail.url) return;\n");
-    combined.push_str("    \n");
-    combined.push_str("    var request = {\n");
-    combined.push_str("      url: detail.url,\n");
-    combined.push_str("      method: detail.method || 'GET',\n");
-    combined.push_str("      headers: detail.headers,\n");
-    combined.push_str("      data: detail.data\n");
-    combined.push_str("    };\n");
-    combined.push_str("    \n");
-    combined.push_str("    if (window.__TAURI__ && window.__TAURI__.core) {\n");
-    combined.push_str("      window.__TAURI__.core.invoke('gm_xhr', { request: request })\n");
-    combined.push_str("        .then(function(response) {\n");
-    combined.push_str("          window.dispatchEvent(new CustomEvent('gm_xhr_response', {\n");
-    combined.push_str("            detail: {\n");
-    combined.push_str("              requestId: detail.requestId,\n");
-    combined.push_str("              responseText: response.response_text,\n");
-    combined.push_str("              status: response.status,\n");
-    combined.push_str("              statusText: response.status_text,\n");
-    combined.push_str("              responseHeaders: response.response_headers\n");
-    combined.push_str("            }\n");
-    combined.push_str("          }));\n");
-    combined.push_str("        })\n");
-    combined.push_str("        .catch(function(error) {\n");
-    combined.push_str("          window.dispatchEvent(new CustomEvent('gm_xhr_response', {\n");
-    combined.push_str("            detail: {\n");
-    combined.push_str("              requestId: detail.requestId,\n");
-    combined.push_str("              error: error.toString()\n");
-    combined.push_str("            }\n");
-    combined.push_str("          }));\n");
-    combined.push_str("        });\n");
-    combined.push_str("    } else {\n");
-    combined.push_str("      console.error('[GM_xmlhttpRequest Bridge] Tauri API not available');\n");
-    combined.push_str("      window.dispatchEvent(new CustomEvent('gm_xhr_response', {\n");
-    combined.push_str("        detail: {\n");
-    combined.push_str("          requestId: detail.requestId,\n");
-    combined.push_str("          error: 'Tauri API not available'\n");
-    combined.push_str("        }\n");
-    combined.push_str("      }));\n");
-    combined.push_str("    }\n");
-    combined.push_str("  });\n");
-    combined.push_str("  console.log('[GeoGuessr Desktop] GM_xmlhttpRequest bridge initialized');\n\n");
+    // Menu command bridge - the manager window runs commands through a gm_menu_command event
+    combined.push_str("  // Menu command bridge - clears stale commands and forwards clicks from the manager window\n");
+    combined.push_str("  if (window.__TAURI__ && window.__TAURI__.core && window.__TAURI__.event) {\n");
+    combined.push_str("    window.__TAURI__.core.invoke('gm_clear_menu_commands')\n");
+    combined.push_str("      .catch(function(e) { console.error('[Menu Commands] Error:', e); });\n");
+    combined.push_str("    window.__TAURI__.event.listen('gm_menu_command', function(event) {\n");
+    combined.push_str("      var command = event.payload;\n");
+    combined.push_str("      if (!command) return;\n");
+    combined.push_str("      window.postMessage({ type: 'gg_menu_command_invoke', scriptId: command.script_id, commandId: command.command_id }, '*');\n");
+    combined.push_str("    });\n");
+    combined.push_str("  }\n\n");
+
+    // Script import bridge - .js files dropped onto the window are imported as userscripts
+    combined.push_str("  // Script import bridge - reports userscript files dropped onto the window, which the app imports\n");
+    combined.push_str("  if (window.__TAURI__ && window.__TAURI__.webviewWindow) {\n");
+    combined.push_str("    window.__TAURI__.webviewWindow.getCurrentWebviewWindow().listen('script_import', function(event) {\n");
+    combined.push_str("      var result = event.payload;\n");
+    combined.push_str("      if (!result) return;\n");
+    combined.push_str("      if (result.script) {\n");
+    combined.push_str("        window.postMessage({ type: 'gg_script_imported', script: result.script }, '*');\n");
+    combined.push_str("      } else {\n");
+    combined.push_str("        window.postMessage({ type: 'gg_script_import_error', path: result.path, error: result.error }, '*');\n");
+    combined.push_str("      }\n");
+    combined.push_str("    });\n");
+    combined.push_str("  }\n\n");
 
     // External URL opener bridge
     combined.push_str("  // External URL opener bridge\n");
@@ -1812,195 +5086,1069 @@ console.log('[GeoGuessr Desktop] Tampermonkey API compatibility loaded');
     // Close main IIFE
     combined.push_str("})();\n");
 
-    combined
+    InjectionScript {
+        code: combined,
+        script_ids,
+    }
+}
+
+// Reload a GeoGuessr window in place so it keeps its position, size and fullscreen state.
+// Its initialization script can't be swapped, so the current scripts go to the tab's
+// sessionStorage, signed with the window's key, and its bootstrap script runs those instead
+fn reload_game_window(window: &tauri::WebviewWindow, state: &AppState) -> Result<(), String> {
+    let window_key = state.window_keys.lock().unwrap()
+        .get(window.label())
+        .cloned()
+        .ok_or_else(|| format!("No scripts were built for window {}", window.label()))?;
+    let injection = get_initialization_script(state, &state.instance(window.label()));
+    let signed = format!("{}\n{}", injection.script_ids.join(","), injection.code);
+    let stored = serde_json::json!({
+        "code": injection.code,
+        "script_ids": injection.script_ids,
+        "mac": hmac_sha256(&window_key, &signed),
+    });
+    let stored_json = serde_json::to_string(&stored.to_string())
+        .map_err(|e| format!("Failed to serialize scripts: {}", e))?;
+    let fetch_key = Uuid::new_v4().simple().to_string();
+    state.fetch_keys.lock().unwrap().insert(window.label().to_string(), fetch_key.clone());
+
+    window.eval(&format!(
+        r#"try {{
+  sessionStorage.setItem('gg_scripts', {});
+}} catch (e) {{
+  // Over the storage quota, have the page fetch them after loading
+  try {{ sessionStorage.setItem('gg_scripts', 'fetch:{}'); }} catch (e) {{}}
+}}
+location.reload();"#,
+        stored_json, fetch_key
+    ))
+    .map_err(|e| format!("Failed to reload window: {}", e))
+}
+
+// Reload every open GeoGuessr window with the current scripts, or open the main one
+#[tauri::command]
+async fn reload_scripts(app: tauri::AppHandle, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let windows = game_windows(&app, &state);
+    if windows.is_empty() {
+        return open_geoguessr(None, app, state).await;
+    }
+
+    for window in windows {
+        reload_game_window(&window, &state)?;
+    }
+    Ok(())
+}
+
+// Current scripts for a window whose hot-reloaded ones didn't fit in sessionStorage
+// (see get_bootstrap_script). Only for a window reload_game_window left a 'fetch' marker in
+#[tauri::command]
+fn get_injection_script(
+    fetch_key: String,
+    window: tauri::WebviewWindow,
+    state: tauri::State<AppState>
+) -> Result<InjectionScript, String> {
+    let expected = state.fetch_keys.lock().unwrap().get(window.label()).cloned();
+    if expected.as_deref() != Some(fetch_key.as_str()) {
+        return Err("No scripts to fetch for this window".to_string());
+    }
+
+    Ok(get_initialization_script(&state, &state.instance(window.label())))
+}
+
+#[tauri::command]
+async fn close_geoguessr(label: Option<String>, app: tauri::AppHandle) -> Result<(), String> {
+    let label = label.unwrap_or_else(|| MAIN_WINDOW_LABEL.to_string());
+    if let Some(window) = app.get_webview_window(&label) {
+        window.close().map_err(|e| format!("Failed to close window: {}", e))?;
+    }
+    Ok(())
+}
+
+// Open URL in default browser
+#[tauri::command]
+async fn open_external_url(url: String) -> Result<(), String> {
+    open::that(&url).map_err(|e| format!("Failed to open URL: {}", e))
+}
+
+// Open Street View in a popup window
+#[tauri::command]
+async fn open_street_view(url: String, app: tauri::AppHandle) -> Result<(), String> {
+    use tauri::WebviewWindowBuilder;
+    use tauri::WebviewUrl;
+
+    // Reuse existing window or create new one
+    if let Some(window) = app.get_webview_window("street_view") {
+        // Window exists - navigate to new URL and focus
+        window.eval(&format!("window.location.href = '{}';", url))
+            .map_err(|e| e.to_string())?;
+        window.set_focus().map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    // Create new popup window
+    WebviewWindowBuilder::new(
+        &app,
+        "street_view",
+        WebviewUrl::External(url.parse().map_err(|_| "Invalid URL".to_string())?)
+    )
+    .title("Street View")
+    .inner_size(1000.0, 700.0)
+    .resizable(true)
+    .decorations(true)  // Native titlebar with close button
+    .on_navigation(move |url| {
+        // Allow Google domains for Street View navigation
+        let host = url.host_str().unwrap_or("");
+        host.contains("google.com") || host.contains("googleapis.com") || host.contains("gstatic.com")
+    })
+    .build()
+    .map_err(|e| format!("Failed to create window: {}", e))?;
+
+    Ok(())
+}
+
+// GM_xmlhttpRequest backend - bypasses CORS by making request from Rust
+#[derive(Debug, Deserialize)]
+struct GmXhrRequest {
+    script_token: String,
+    url: String,
+    method: Option<String>,
+    headers: Option<HashMap<String, String>>,
+    data: Option<String>,
+    // Binary request bodies (ArrayBuffer, typed arrays, Blob) arrive base64 encoded
+    #[serde(default)]
+    data_base64: Option<String>,
+    // Milliseconds, like GM_xmlhttpRequest's timeout option
+    #[serde(default)]
+    timeout: Option<u64>,
+}
+
+// Streamed to the page over a channel, mirroring XMLHttpRequest's ready states
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum GmXhrEvent {
+    // Sent first, with the id abort() has to use
+    Started {
+        request_id: String,
+    },
+    Headers {
+        status: u16,
+        status_text: String,
+        response_headers: String,
+        final_url: String,
+        total: Option<u64>,
+    },
+    Progress {
+        loaded: u64,
+        total: Option<u64>,
+        data: String,
+    },
+    Load,
+    Timeout,
+    Abort,
+    Error {
+        error: String,
+    },
+}
+
+// Ask the user whether a script may contact a host it didn't declare with @connect
+#[derive(Debug, Clone)]
+enum ConnectDecision {
+    AlwaysAllow,
+    AllowOnce,
+    Deny,
+}
+
+async fn prompt_connect(app: &tauri::AppHandle, script_name: &str, host: &str) -> ConnectDecision {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind, MessageDialogResult};
+
+    const ALWAYS: &str = "Always allow";
+    const ONCE: &str = "Allow once";
+    const DENY: &str = "Block";
+
+    let dialog = app.dialog()
+        .message(format!(
+            "The script \"{}\" wants to connect to {}, which it did not declare with @connect.\n\nOnly allow this if you trust the script.",
+            script_name, host
+        ))
+        .title("Cross-origin request")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::YesNoCancelCustom(ALWAYS.to_string(), ONCE.to_string(), DENY.to_string()));
+
+    let result = tauri::async_runtime::spawn_blocking(move || dialog.blocking_show_with_result())
+        .await
+        .unwrap_or(MessageDialogResult::Cancel);
+
+    match result {
+        MessageDialogResult::Yes => ConnectDecision::AlwaysAllow,
+        MessageDialogResult::No => ConnectDecision::AllowOnce,
+        MessageDialogResult::Custom(label) if label == ALWAYS => ConnectDecision::AlwaysAllow,
+        MessageDialogResult::Custom(label) if label == ONCE => ConnectDecision::AllowOnce,
+        _ => ConnectDecision::Deny,
+    }
+}
+
+// Hosts outside @connect need the user's approval. Returns the host if it was only allowed
+// for this one request
+async fn authorize_connect(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    script: &UserScript,
+    host: &str
+) -> Result<Option<String>, String> {
+    if script.connect_allowed(host) {
+        return Ok(None);
+    }
+
+    let denial = (script.id.clone(), host.to_string());
+    if state.denied_connects.lock().unwrap().contains(&denial) {
+        return Err(format!("Connection to {} was blocked by the user", host));
+    }
+
+    // Requests made while the prompt is open take its answer
+    let waiting = {
+        let mut connect_prompts = state.connect_prompts.lock().unwrap();
+        match connect_prompts.get(&denial) {
+            Some(prompt) => Some(prompt.subscribe()),
+            None => {
+                connect_prompts.insert(denial.clone(), tokio::sync::broadcast::channel(1).0);
+                None
+            }
+        }
+    };
+    let decision = match waiting {
+        Some(mut answer) => answer.recv().await.unwrap_or(ConnectDecision::Deny),
+        None => {
+            let decision = prompt_connect(app, &script.name, host).await;
+            if let Some(prompt) = state.connect_prompts.lock().unwrap().remove(&denial) {
+                let _ = prompt.send(decision.clone());
+            }
+            decision
+        }
+    };
+
+    match decision {
+        ConnectDecision::AlwaysAllow => {
+            let mut scripts = state.scripts.lock().unwrap();
+            if let Some(stored) = scripts.iter_mut().find(|s| s.id == script.id) {
+                if !stored.user_connects.iter().any(|h| h == host) {
+                    stored.user_connects.push(host.to_string());
+                }
+            }
+            state.save_scripts(&scripts)?;
+            Ok(None)
+        }
+        ConnectDecision::AllowOnce => Ok(Some(host.to_string())),
+        ConnectDecision::Deny => {
+            state.denied_connects.lock().unwrap().insert(denial);
+            Err(format!("Connection to {} was blocked by the user", host))
+        }
+    }
+}
+
+// Redirects must stay within the allowed hosts too
+fn connect_redirect_policy(script: UserScript, allowed_once: Option<String>) -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() >= 10 {
+            return attempt.error("Too many redirects");
+        }
+        let redirect_host = attempt.url().host_str().unwrap_or("").to_string();
+        if script.connect_allowed(&redirect_host) || allowed_once.as_deref() == Some(redirect_host.as_str()) {
+            attempt.follow()
+        } else {
+            attempt.error(format!("Redirect to {} is not allowed by @connect", redirect_host))
+        }
+    })
+}
+
+#[tauri::command]
+async fn gm_xhr(
+    request: GmXhrRequest,
+    on_event: tauri::ipc::Channel<GmXhrEvent>,
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, AppState>
+) -> Result<(), String> {
+    let url: reqwest::Url = request.url.parse()
+        .map_err(|e| format!("Invalid URL: {}", e))?;
+    let host = url.host_str()
+        .ok_or_else(|| "URL has no host".to_string())?
+        .to_string();
+
+    // Only scripts granted GM_xmlhttpRequest may make CORS-bypassing requests
+    let script = Some(state.token_script(&window, &request.script_token)?)
+        .filter(|script| {
+            let grants = script.granted_apis();
+            grants.contains(&"GM_xmlhttpRequest") || grants.contains(&"GM.xmlHttpRequest")
+        })
+        .ok_or_else(|| "GM_xmlhttpRequest/GM.xmlHttpRequest has not been granted to this script".to_string())?;
+
+    // Registered before the connect prompt so abort() works while it's open too
+    let request_id = Uuid::new_v4().to_string();
+    state.xhr_requests.lock().unwrap().insert(request_id.clone(), None);
+    let outcome = async {
+        on_event.send(GmXhrEvent::Started { request_id: request_id.clone() })
+            .map_err(|e| format!("Failed to send response: {}", e))?;
+
+        let allowed_once = authorize_connect(&app, &state, &script, &host).await?;
+        let redirect_policy = connect_redirect_policy(script, allowed_once);
+
+        let client = reqwest::Client::builder()
+            .redirect(redirect_policy)
+            .dns_resolver(std::sync::Arc::new(PublicHostResolver))
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+        let method = request.method.unwrap_or_else(|| "GET".to_string());
+        let mut req_builder = match method.to_uppercase().as_str() {
+            "POST" => client.post(url),
+            "PUT" => client.put(url),
+            "DELETE" => client.delete(url),
+            "HEAD" => client.head(url),
+            "PATCH" => client.patch(url),
+            _ => client.get(url),
+        };
+
+        // Add custom headers
+        if let Some(headers) = request.headers {
+            for (key, value) in headers {
+                req_builder = req_builder.header(&key, &value);
+            }
+        }
+
+        // Add body data for POST/PUT/PATCH
+        if let Some(data_base64) = request.data_base64 {
+            use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+            let bytes = BASE64.decode(data_base64)
+                .map_err(|e| format!("Invalid binary request body: {}", e))?;
+            req_builder = req_builder.body(bytes);
+        } else if let Some(data) = request.data {
+            req_builder = req_builder.body(data);
+        }
+
+        // Run the transfer as its own task so gm_xhr_abort and the timeout can cancel it,
+        // unless it was aborted already
+        let task = {
+            let mut xhr_requests = state.xhr_requests.lock().unwrap();
+            let Some(entry) = xhr_requests.get_mut(&request_id) else {
+                return Ok(GmXhrEvent::Abort);
+            };
+            let task = tokio::spawn(stream_gm_xhr(req_builder, on_event.clone()));
+            *entry = Some(task.abort_handle());
+            task
+        };
+        let abort_handle = task.abort_handle();
+
+        let result = match request.timeout.filter(|ms| *ms > 0) {
+            Some(ms) => tokio::time::timeout(std::time::Duration::from_millis(ms), task).await,
+            None => Ok(task.await),
+        };
+
+        Ok::<_, String>(match result {
+            Err(_) => {
+                abort_handle.abort();
+                GmXhrEvent::Timeout
+            }
+            Ok(Err(e)) if e.is_cancelled() => GmXhrEvent::Abort,
+            Ok(Err(e)) => GmXhrEvent::Error { error: format!("Request task failed: {}", e) },
+            Ok(Ok(Err(error))) => GmXhrEvent::Error { error },
+            Ok(Ok(Ok(()))) => GmXhrEvent::Load,
+        })
+    }
+    .await;
+    state.xhr_requests.lock().unwrap().remove(&request_id);
+
+    on_event.send(outcome?).map_err(|e| format!("Failed to send response: {}", e))
+}
+
+async fn stream_gm_xhr(
+    req_builder: reqwest::RequestBuilder,
+    on_event: tauri::ipc::Channel<GmXhrEvent>
+) -> Result<(), String> {
+    use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+
+    let mut response = req_builder.send().await
+        .map_err(|e| format!("Request failed: {}", e))?;
+
+    let status = response.status().as_u16();
+    let status_text = response.status().canonical_reason().unwrap_or("").to_string();
+    let total = response.content_length();
+
+    // Collect response headers
+    let response_headers: Vec<String> = response.headers()
+        .iter()
+        .map(|(k, v)| format!("{}: {}", k.as_str(), v.to_str().unwrap_or("")))
+        .collect();
+
+    on_event.send(GmXhrEvent::Headers {
+        status,
+        status_text,
+        response_headers: response_headers.join("\r\n"),
+        final_url: response.url().to_string(),
+        total,
+    }).map_err(|e| format!("Failed to send response: {}", e))?;
+
+    // Stream the body as it arrives, the page decodes it according to responseType
+    let mut loaded = 0u64;
+    while let Some(chunk) = response.chunk().await
+        .map_err(|e| format!("Failed to read response: {}", e))? {
+        loaded += chunk.len() as u64;
+        on_event.send(GmXhrEvent::Progress {
+            loaded,
+            total,
+            data: BASE64.encode(&chunk),
+        }).map_err(|e| format!("Failed to send response: {}", e))?;
+    }
+
+    Ok(())
 }
 
 #[tauri::command]
-async fn reload_scripts(app: tauri::AppHandle, state: tauri::State<'_, AppState>) -> Result<(), String> {
-    // Set reloading flag to prevent app exit
-    set_reloading(true);
+fn gm_xhr_abort(request_id: String, state: tauri::State<AppState>) -> Result<(), String> {
+    if let Some(Some(abort_handle)) = state.xhr_requests.lock().unwrap().remove(&request_id) {
+        abort_handle.abort();
+    }
+    Ok(())
+}
 
-    // Get fresh initialization script
-    let init_script = get_initialization_script(&state);
+// A GM_notification call from the page
+#[derive(Debug, Deserialize)]
+struct GmNotification {
+    script_token: String,
+    #[serde(default)]
+    title: Option<String>,
+    text: String,
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    timeout: Option<u64>,
+}
 
-    // Close old window if it exists
-    if let Some(window) = app.get_webview_window("geoguessr") {
-        let _ = window.close();
-    }
+// Sent back to the page, which maps them to onclick and ondone
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum GmNotificationEvent {
+    Click,
+    Done,
+}
 
-    // Small delay to allow window to close
-    tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+// How long a notification counts as shown where the platform can't tell us it was closed
+const NOTIFICATION_DEFAULT_TIMEOUT_MS: u64 = 6000;
+
+// The bytes of a notification image given as a data: or http(s) URL. Remote images are
+// fetched like GM_xmlhttpRequest requests, only from hosts the script may connect to
+async fn notification_image(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    script: &UserScript,
+    image: &str
+) -> Result<(String, Vec<u8>), String> {
+    use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 
-    // Create new window with updated scripts
-    let result = WebviewWindowBuilder::new(
-        &app,
-        "geoguessr",
-        WebviewUrl::External("https://www.geoguessr.com/".parse().unwrap())
-    )
-        .title("GeoGuessr Desktop")
-        .inner_size(1400.0, 900.0)
-        .resizable(true)
-        .decorations(false)
-        .initialization_script(&init_script)
-        .on_navigation(move |url| {
-            url.host_str() == Some("www.geoguessr.com") ||
-            url.host_str() == Some("geoguessr.com")
-        })
+    if let Some(data_url) = image.strip_prefix("data:") {
+        let (header, data) = data_url.split_once(',')
+            .ok_or_else(|| "Invalid data URL".to_string())?;
+        let mime_type = header.split(';').next().unwrap_or("").to_string();
+        let bytes = if header.ends_with(";base64") {
+            BASE64.decode(data).map_err(|e| format!("Invalid data URL: {}", e))?
+        } else {
+            data.as_bytes().to_vec()
+        };
+        return Ok((mime_type, bytes));
+    }
+
+    let url: reqwest::Url = image.parse()
+        .map_err(|_| format!("Unsupported notification image: {}", image))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(format!("Unsupported notification image: {}", image));
+    }
+    let host = url.host_str()
+        .ok_or_else(|| "URL has no host".to_string())?
+        .to_string();
+
+    let allowed_once = authorize_connect(app, state, script, &host).await?;
+    let client = reqwest::Client::builder()
+        .redirect(connect_redirect_policy(script.clone(), allowed_once))
+        .dns_resolver(std::sync::Arc::new(PublicHostResolver))
+        .timeout(std::time::Duration::from_secs(30))
         .build()
-        .map_err(|e| format!("Failed to create window: {}", e));
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let response = client.get(url).send().await
+        .map_err(|e| format!("Failed to fetch notification image: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Failed to fetch notification image: HTTP {}", response.status().as_u16()));
+    }
 
-    // Reset reloading flag
-    set_reloading(false);
+    let mime_type = response.headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(';').next().unwrap_or("").trim().to_string())
+        .unwrap_or_default();
+    let bytes = response.bytes().await
+        .map_err(|e| format!("Failed to fetch notification image: {}", e))?;
+    Ok((mime_type, bytes.to_vec()))
+}
 
-    result.map(|_| ())
+// Native notifications need an image file, so write the image to a temp file
+fn notification_image_file(mime_type: &str, bytes: &[u8]) -> Result<PathBuf, String> {
+    let extension = match mime_type {
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/svg+xml" => "svg",
+        "image/webp" => "webp",
+        _ => "png",
+    };
+    let path = std::env::temp_dir().join(format!("geoguessrdesktop-notification-{}.{}", Uuid::new_v4(), extension));
+    fs::write(&path, bytes).map_err(|e| format!("Failed to write notification image: {}", e))?;
+    Ok(path)
 }
 
 #[tauri::command]
-async fn close_geoguessr(app: tauri::AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("geoguessr") {
-        window.close().map_err(|e| format!("Failed to close window: {}", e))?;
+async fn gm_notification(
+    notification: GmNotification,
+    on_event: tauri::ipc::Channel<GmNotificationEvent>,
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, AppState>
+) -> Result<(), String> {
+    let script = state.token_script(&window, &notification.script_token)?;
+    let granted = script.granted_apis();
+    if !granted.contains(&"GM_notification") && !granted.contains(&"GM.notification") {
+        return Err(format!("{} is not granted GM_notification", script.name));
+    }
+
+    let image_file = match notification.image.as_deref().filter(|image| !image.is_empty()) {
+        Some(image) => {
+            let file = notification_image(&app, &state, &script, image).await
+                .and_then(|(mime_type, bytes)| notification_image_file(&mime_type, &bytes));
+            match file {
+                Ok(path) => Some(path),
+                Err(e) => {
+                    eprintln!("[GM_notification] {}", e);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    let timeout_ms = notification.timeout.unwrap_or(NOTIFICATION_DEFAULT_TIMEOUT_MS);
+    let mut native = notify_rust::Notification::new();
+    native
+        .appname("GeoGuessr Desktop")
+        .summary(notification.title.as_deref().unwrap_or(&script.name))
+        .body(&notification.text)
+        .timeout(notify_rust::Timeout::Milliseconds(timeout_ms.min(u32::MAX as u64) as u32));
+    #[cfg(not(target_os = "macos"))]
+    if let Some(path) = &image_file {
+        native.image_path(&path.to_string_lossy());
+    }
+
+    // Only XDG desktops report clicks and closes back, elsewhere the notification is done
+    // once its timeout has passed
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        native.action("default", "Open");
+        let handle = native.show().map_err(|e| format!("Failed to show notification: {}", e))?;
+        tauri::async_runtime::spawn_blocking(move || {
+            handle.wait_for_action(|action| {
+                if action == "default" {
+                    let _ = window.set_focus();
+                    let _ = on_event.send(GmNotificationEvent::Click);
+                }
+            });
+            let _ = on_event.send(GmNotificationEvent::Done);
+            if let Some(path) = image_file {
+                let _ = fs::remove_file(path);
+            }
+        });
+    }
+
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    {
+        let _ = &window;
+        native.show().map_err(|e| format!("Failed to show notification: {}", e))?;
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(timeout_ms)).await;
+            let _ = on_event.send(GmNotificationEvent::Done);
+            if let Some(path) = image_file {
+                let _ = fs::remove_file(path);
+            }
+        });
     }
+
     Ok(())
 }
 
-// Open URL in default browser
+// @resource content for GM_getResourceText/GM_getResourceURL, fetched now if it isn't cached yet
 #[tauri::command]
-async fn open_external_url(url: String) -> Result<(), String> {
-    open::that(&url).map_err(|e| format!("Failed to open URL: {}", e))
+async fn gm_get_resource(
+    script_token: String,
+    name: String,
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, AppState>
+) -> Result<Option<serde_json::Value>, String> {
+    let script = state.token_script(&window, &script_token)?;
+    if !reads_resources(&script) {
+        return Err(format!("{} is not granted GM_getResourceText/GM_getResourceURL", script.name));
+    }
+    let Some(resource) = script.resources.into_iter().find(|resource| resource.name == name) else {
+        return Ok(None);
+    };
+
+    let cached = state.resources.lock().unwrap().get(&resource.url).cloned();
+    let cached = match cached {
+        Some(cached) => cached,
+        None => {
+            let url = resource.url.clone();
+            let fetched = tauri::async_runtime::spawn_blocking(move || fetch_resource_from_url(&url))
+                .await
+                .map_err(|e| format!("Failed to fetch resource: {}", e))?
+                .map_err(|e| format!("Failed to fetch resource {} ({}): {}", resource.name, resource.url, e))?;
+            let mut resources = state.resources.lock().unwrap();
+            resources.insert(resource.url.clone(), fetched.clone());
+            state.save_resources(&resources)?;
+            fetched
+        }
+    };
+
+    Ok(Some(serde_json::json!({
+        "mimeType": cached.mime_type,
+        "data": cached.data,
+    })))
+}
+
+// Fetch @require and @resource files of installed scripts that aren't cached, e.g. ones named
+// in headers that older versions didn't parse or ones dropped for failing their integrity
+// hashes. Runs in the background at startup
+fn resolve_missing_resources(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let missing_requires: Vec<String> = {
+            let scripts = state.scripts.lock().unwrap();
+            let dependencies = state.dependencies.lock().unwrap();
+            scripts.iter()
+                .flat_map(|script| script.requires.iter())
+                .filter(|url| !dependencies.contains_key(*url))
+                .cloned()
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect()
+        };
+
+        let mut fetched_requires = HashMap::new();
+        for url in missing_requires {
+            match fetch_dependency(&url) {
+                Ok(dependency) => {
+                    fetched_requires.insert(url, dependency);
+                }
+                Err(e) => eprintln!("[Dependencies] Failed to fetch {}: {}", url, e),
+            }
+        }
+        if !fetched_requires.is_empty() {
+            let mut dependencies = state.dependencies.lock().unwrap();
+            dependencies.extend(fetched_requires);
+            if let Err(e) = state.save_dependencies(&dependencies) {
+                eprintln!("[Dependencies] {}", e);
+            }
+        }
+
+        let missing: Vec<ScriptResource> = {
+            let scripts = state.scripts.lock().unwrap();
+            let resources = state.resources.lock().unwrap();
+            scripts.iter()
+                .flat_map(|script| script.resources.iter())
+                .filter(|resource| !resources.contains_key(&resource.url))
+                .cloned()
+                .collect()
+        };
+
+        let mut fetched = HashMap::new();
+        for resource in missing {
+            if fetched.contains_key(&resource.url) {
+                continue;
+            }
+            match fetch_resource_from_url(&resource.url) {
+                Ok(cached) => {
+                    fetched.insert(resource.url, cached);
+                }
+                Err(e) => eprintln!("[Resources] Failed to fetch {} ({}): {}", resource.name, resource.url, e),
+            }
+        }
+        if fetched.is_empty() {
+            return;
+        }
+
+        let mut resources = state.resources.lock().unwrap();
+        resources.extend(fetched);
+        if let Err(e) = state.save_resources(&resources) {
+            eprintln!("[Resources] {}", e);
+        }
+    });
 }
 
-// Open Street View in a popup window
 #[tauri::command]
-async fn open_street_view(url: String, app: tauri::AppHandle) -> Result<(), String> {
-    use tauri::WebviewWindowBuilder;
-    use tauri::WebviewUrl;
+fn gm_set_clipboard(
+    script_token: String,
+    data: String,
+    mime_type: Option<String>,
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: tauri::State<AppState>
+) -> Result<(), String> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
 
-    // Reuse existing window or create new one
-    if let Some(window) = app.get_webview_window("street_view") {
-        // Window exists - navigate to new URL and focus
-        window.eval(&format!("window.location.href = '{}';", url))
-            .map_err(|e| e.to_string())?;
-        window.set_focus().map_err(|e| e.to_string())?;
-        return Ok(());
+    let script = state.token_script(&window, &script_token)?;
+    let granted = script.granted_apis();
+    if !granted.contains(&"GM_setClipboard") && !granted.contains(&"GM.setClipboard") {
+        return Err(format!("{} is not granted GM_setClipboard", script.name));
     }
 
-    // Create new popup window
-    WebviewWindowBuilder::new(
-        &app,
-        "street_view",
-        WebviewUrl::External(url.parse().map_err(|_| "Invalid URL".to_string())?)
-    )
-    .title("Street View")
-    .inner_size(1000.0, 700.0)
-    .resizable(true)
-    .decorations(true)  // Native titlebar with close button
-    .on_navigation(move |url| {
-        // Allow Google domains for Street View navigation
-        let host = url.host_str().unwrap_or("");
-        host.contains("google.com") || host.contains("googleapis.com") || host.contains("gstatic.com")
-    })
-    .build()
-    .map_err(|e| format!("Failed to create window: {}", e))?;
-
-    Ok(())
+    let result = if mime_type.as_deref() == Some("text/html") {
+        app.clipboard().write_html(data, None)
+    } else {
+        app.clipboard().write_text(data)
+    };
+    result.map_err(|e| format!("Failed to write to clipboard: {}", e))
 }
 
-// GM_xmlhttpRequest backend - bypasses CORS by making request from Rust
+// A GM_download call. Remote files come as a url, blob: and data: URLs are read by the page
+// and sent as data_base64
 #[derive(Debug, Deserialize)]
-struct GmXhrRequest {
-    url: String,
-    method: Option<String>,
+struct GmDownloadRequest {
+    download_id: String,
+    script_token: String,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    data_base64: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
     headers: Option<HashMap<String, String>>,
-    data: Option<String>,
+    #[serde(default)]
+    save_as: bool,
+    // "uniquify" (default), "overwrite" or "prompt"
+    #[serde(default)]
+    conflict_action: Option<String>,
+    #[serde(default)]
+    timeout: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum GmDownloadEvent {
+    Progress {
+        loaded: u64,
+        total: Option<u64>,
+    },
+    Load {
+        path: String,
+    },
+    Timeout,
+    Abort,
+    Error {
+        error: String,
+    },
+}
+
+// Make a script supplied name safe to use as a file name on every platform
+fn sanitize_filename(name: &str) -> String {
+    const MAX_LENGTH: usize = 200;
+
+    let cleaned: String = name.chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let mut cleaned = cleaned.trim().trim_matches('.').trim().to_string();
+    if cleaned.is_empty() {
+        return "download".to_string();
+    }
+
+    // Shorten overly long names but keep the extension
+    if cleaned.chars().count() > MAX_LENGTH {
+        let extension = cleaned.rfind('.')
+            .map(|i| cleaned[i..].to_string())
+            .filter(|ext| ext.chars().count() <= 16)
+            .unwrap_or_default();
+        let stem: String = cleaned.chars().take(MAX_LENGTH - extension.chars().count()).collect();
+        cleaned = format!("{}{}", stem.trim_end(), extension);
+    }
+
+    // Windows refuses device names like CON or COM1, whatever the extension
+    let stem = cleaned.split('.').next().unwrap_or("").to_ascii_uppercase();
+    let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.len() == 4
+            && matches!(stem.as_bytes()[3], b'1'..=b'9'));
+    if reserved {
+        cleaned.insert(0, '_');
+    }
+    cleaned
+}
+
+// "name.ext" becomes "name (1).ext", "name (2).ext", ... until the name is free. The file is
+// created empty right away, so two downloads running at once can't pick the same name
+fn unique_download_path(dir: &std::path::Path, filename: &str) -> Result<PathBuf, String> {
+    let (stem, extension) = match filename.rfind('.') {
+        Some(i) if i > 0 => (&filename[..i], &filename[i..]),
+        _ => (filename, ""),
+    };
+    for n in 0.. {
+        let path = match n {
+            0 => dir.join(filename),
+            n => dir.join(format!("{} ({}){}", stem, n, extension)),
+        };
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => return Ok(path),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to create download file: {}", e)),
+        }
+    }
+    unreachable!()
 }
 
-#[derive(Debug, Serialize)]
-struct GmXhrResponse {
-    response_text: String,
-    status: u16,
-    status_text: String,
-    response_headers: String,
+async fn prompt_save_path(app: &tauri::AppHandle, dir: &std::path::Path, filename: &str) -> Option<PathBuf> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let dialog = app.dialog()
+        .file()
+        .set_title("Save download")
+        .set_directory(dir)
+        .set_file_name(filename);
+
+    tauri::async_runtime::spawn_blocking(move || dialog.blocking_save_file())
+        .await
+        .ok()
+        .flatten()
+        .and_then(|path| path.into_path().ok())
 }
 
 #[tauri::command]
-async fn gm_xhr(request: GmXhrRequest) -> Result<GmXhrResponse, String> {
-    let client = reqwest::Client::new();
-
-    let method = request.method.unwrap_or_else(|| "GET".to_string());
-    let mut req_builder = match method.to_uppercase().as_str() {
-        "POST" => client.post(&request.url),
-        "PUT" => client.put(&request.url),
-        "DELETE" => client.delete(&request.url),
-        "HEAD" => client.head(&request.url),
-        "PATCH" => client.patch(&request.url),
-        _ => client.get(&request.url),
+async fn gm_download(
+    request: GmDownloadRequest,
+    on_event: tauri::ipc::Channel<GmDownloadEvent>,
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, AppState>
+) -> Result<(), String> {
+    use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+
+    let script = Some(state.token_script(&window, &request.script_token)?)
+        .filter(|script| {
+            let grants = script.granted_apis();
+            grants.contains(&"GM_download") || grants.contains(&"GM.download")
+        })
+        .ok_or_else(|| "GM_download/GM.download has not been granted to this script".to_string())?;
+
+    let url: Option<reqwest::Url> = match &request.url {
+        Some(url) => Some(url.parse().map_err(|e| format!("Invalid URL: {}", e))?),
+        None => None,
+    };
+    if url.is_none() && request.data_base64.is_none() {
+        return Err("GM_download needs a url".to_string());
+    }
+
+    let requested_name = request.name.as_deref()
+        .filter(|name| !name.trim().is_empty())
+        .or_else(|| url.as_ref()
+            .and_then(|url| url.path_segments())
+            .and_then(|mut segments| segments.next_back())
+            .filter(|segment| !segment.is_empty()))
+        .unwrap_or("download");
+    let filename = sanitize_filename(requested_name);
+
+    let dir = state.download_dir();
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create download folder: {}", e))?;
+
+    let conflict_action = request.conflict_action.as_deref().unwrap_or("uniquify");
+    let (path, reserved) = if request.save_as || (conflict_action == "prompt" && dir.join(&filename).exists()) {
+        match prompt_save_path(&app, &dir, &filename).await {
+            Some(path) => (path, false),
+            None => return on_event.send(GmDownloadEvent::Abort)
+                .map_err(|e| format!("Failed to send response: {}", e)),
+        }
+    } else if conflict_action == "overwrite" {
+        (dir.join(&filename), false)
+    } else {
+        (unique_download_path(&dir, &filename)?, true)
     };
 
-    // Add custom headers
+    // Content the page already has is written straight away
+    if let Some(data_base64) = request.data_base64 {
+        let bytes = BASE64.decode(data_base64)
+            .map_err(|e| format!("Invalid download data: {}", e))?;
+        let total = bytes.len() as u64;
+        fs::write(&path, bytes)
+            .map_err(|e| format!("Failed to save download: {}", e))?;
+        let _ = on_event.send(GmDownloadEvent::Progress { loaded: total, total: Some(total) });
+        return on_event.send(GmDownloadEvent::Load { path: path.to_string_lossy().to_string() })
+            .map_err(|e| format!("Failed to send response: {}", e));
+    }
+
+    let url = url.unwrap();
+    let host = url.host_str()
+        .ok_or_else(|| "URL has no host".to_string())?
+        .to_string();
+    let client = match authorize_connect(&app, &state, &script, &host).await {
+        Ok(allowed_once) => reqwest::Client::builder()
+            .redirect(connect_redirect_policy(script, allowed_once))
+            .dns_resolver(std::sync::Arc::new(PublicHostResolver))
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e)),
+        Err(e) => Err(e),
+    };
+    let client = match client {
+        Ok(client) => client,
+        Err(e) => {
+            // Give back the name reserved by unique_download_path
+            if reserved {
+                let _ = fs::remove_file(&path);
+            }
+            return Err(e);
+        }
+    };
+    let mut req_builder = client.get(url);
     if let Some(headers) = request.headers {
         for (key, value) in headers {
             req_builder = req_builder.header(&key, &value);
         }
     }
 
-    // Add body data for POST/PUT/PATCH
-    if let Some(data) = request.data {
-        req_builder = req_builder.body(data);
-    }
-
-    let response = req_builder.send().await
-        .map_err(|e| format!("Request failed: {}", e))?;
-
-    let status = response.status().as_u16();
-    let status_text = response.status().canonical_reason().unwrap_or("").to_string();
-
-    // Collect response headers
-    let response_headers: Vec<String> = response.headers()
-        .iter()
-        .map(|(k, v)| format!("{}: {}", k.as_str(), v.to_str().unwrap_or("")))
-        .collect();
+    // Download next to the target and only move it into place once complete
+    let part_path = PathBuf::from(format!("{}.part", path.to_string_lossy()));
+    let task = tokio::spawn(stream_gm_download(req_builder, part_path.clone(), on_event.clone()));
+    let abort_handle = task.abort_handle();
+    state.downloads.lock().unwrap().insert(request.download_id.clone(), abort_handle.clone());
 
-    let response_text = response.text().await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
+    let result = match request.timeout.filter(|ms| *ms > 0) {
+        Some(ms) => tokio::time::timeout(std::time::Duration::from_millis(ms), task).await,
+        None => Ok(task.await),
+    };
+    state.downloads.lock().unwrap().remove(&request.download_id);
 
-    Ok(GmXhrResponse {
-        response_text,
-        status,
-        status_text,
-        response_headers: response_headers.join("\r\n"),
-    })
+    let outcome = match result {
+        Err(_) => {
+            abort_handle.abort();
+            GmDownloadEvent::Timeout
+        }
+        Ok(Err(e)) if e.is_cancelled() => GmDownloadEvent::Abort,
+        Ok(Err(e)) => GmDownloadEvent::Error { error: format!("Download task failed: {}", e) },
+        Ok(Ok(Err(error))) => GmDownloadEvent::Error { error },
+        Ok(Ok(Ok(()))) => match fs::rename(&part_path, &path) {
+            Ok(()) => GmDownloadEvent::Load { path: path.to_string_lossy().to_string() },
+            Err(e) => GmDownloadEvent::Error { error: format!("Failed to save download: {}", e) },
+        },
+    };
+    if !matches!(outcome, GmDownloadEvent::Load { .. }) {
+        let _ = fs::remove_file(&part_path);
+        // Give back the name reserved by unique_download_path
+        if reserved {
+            let _ = fs::remove_file(&path);
+        }
+    }
+    on_event.send(outcome).map_err(|e| format!("Failed to send response: {}", e))
 }
 
-use std::sync::atomic::{AtomicBool, Ordering};
+async fn stream_gm_download(
+    req_builder: reqwest::RequestBuilder,
+    part_path: PathBuf,
+    on_event: tauri::ipc::Channel<GmDownloadEvent>
+) -> Result<(), String> {
+    use std::io::Write;
+
+    let mut response = req_builder.send().await
+        .map_err(|e| format!("Download failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Download failed with status {}", response.status()));
+    }
 
-static RELOADING: AtomicBool = AtomicBool::new(false);
+    let total = response.content_length();
+    let mut file = fs::File::create(&part_path)
+        .map_err(|e| format!("Failed to create download file: {}", e))?;
+    let mut loaded = 0u64;
+    while let Some(chunk) = response.chunk().await
+        .map_err(|e| format!("Download failed: {}", e))? {
+        file.write_all(&chunk)
+            .map_err(|e| format!("Failed to write download file: {}", e))?;
+        loaded += chunk.len() as u64;
+        on_event.send(GmDownloadEvent::Progress { loaded, total })
+            .map_err(|e| format!("Failed to send response: {}", e))?;
+    }
 
-pub fn set_reloading(value: bool) {
-    RELOADING.store(value, Ordering::SeqCst);
+    Ok(())
 }
 
-pub fn is_reloading() -> bool {
-    RELOADING.load(Ordering::SeqCst)
+#[tauri::command]
+fn gm_download_abort(download_id: String, state: tauri::State<AppState>) -> Result<(), String> {
+    if let Some(abort_handle) = state.downloads.lock().unwrap().remove(&download_id) {
+        abort_handle.abort();
+    }
+    Ok(())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .manage(AppState::new())
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) = event {
+                handle_dropped_files(window, paths);
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             get_scripts,
             add_script_from_url,
+            add_script_from_file,
+            create_script,
+            update_script_code,
+            get_dependencies,
+            link_script_file,
+            unlink_script_file,
             toggle_script,
             delete_script,
             reorder_script,
             refresh_script,
             auto_update_scripts,
+            get_script_update_diff,
+            accept_script_update,
+            reject_script_update,
+            get_script_history,
+            rollback_script,
+            set_script_pinned,
+            gm_get_values,
+            gm_set_value,
+            gm_delete_value,
+            gm_register_menu_command,
+            gm_unregister_menu_command,
+            gm_clear_menu_commands,
+            get_menu_commands,
+            run_menu_command,
+            get_settings,
+            update_settings,
             get_data_dir,
+            get_instances,
+            create_instance,
+            update_instance,
+            delete_instance,
             open_geoguessr,
             reload_scripts,
+            get_injection_script,
             close_geoguessr,
             gm_xhr,
+            gm_xhr_abort,
+            gm_notification,
+            gm_get_resource,
+            gm_set_clipboard,
+            gm_download,
+            gm_download_abort,
             open_external_url,
             open_street_view,
             discord_connect,
@@ -2009,37 +6157,190 @@ pub fn run() {
             discord_disconnect
         ])
         .setup(|app| {
+            if let Err(e) = watch_linked_scripts(app.handle()) {
+                eprintln!("[Script watcher] {}", e);
+            }
+            resolve_missing_resources(app.handle());
+
             // Open GeoGuessr window on startup
             let state = app.state::<AppState>();
-            let init_script = get_initialization_script(&state);
-
-            let _window = WebviewWindowBuilder::new(
-                app,
-                "geoguessr",
-                WebviewUrl::External("https://www.geoguessr.com/".parse().unwrap())
-            )
-                .title("GeoGuessr Desktop")
-                .inner_size(1400.0, 900.0)
-                .resizable(true)
-                .decorations(false) // Custom titlebar
-                .initialization_script(&init_script)
-                .on_navigation(move |url| {
-                    // Allow navigation to geoguessr.com domains
-                    url.host_str() == Some("www.geoguessr.com") ||
-                    url.host_str() == Some("geoguessr.com")
-                })
-                .build()?;
+            let instance = state.instance(MAIN_WINDOW_LABEL);
+            build_game_window(app.handle(), GameWindowConfig::for_instance(&state, &instance))?;
 
             Ok(())
         })
-        .build(tauri::generate_context!())
-        .expect("error while building tauri application")
-        .run(|_app, event| {
-            if let tauri::RunEvent::ExitRequested { api, .. } = event {
-                // Prevent exit if we're in the middle of reloading
-                if is_reloading() {
-                    api.prevent_exit();
-                }
-            }
-        });
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The loader tests rules case-insensitively, see testUrlRules
+    fn rule_matches(source: &str, url: &str) -> bool {
+        regex::RegexBuilder::new(source)
+            .case_insensitive(true)
+            .build()
+            .unwrap()
+            .is_match(url)
+    }
+
+    fn script_with_rules(matches: &[&str], includes: &[&str]) -> UserScript {
+        serde_json::from_value(serde_json::json!({
+            "id": "test",
+            "name": "Test",
+            "code": "",
+            "enabled": true,
+            "matches": matches,
+            "includes": includes,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn match_pattern_any_scheme_and_subdomains() {
+        let regex = match_pattern_to_regex("*://*.geoguessr.com/*").unwrap();
+        assert!(rule_matches(&regex, "https://www.geoguessr.com/duels"));
+        assert!(rule_matches(&regex, "http://geoguessr.com/"));
+        assert!(!rule_matches(&regex, "https://notgeoguessr.com/"));
+        assert!(!rule_matches(&regex, "ftp://www.geoguessr.com/"));
+        assert!(!rule_matches(&regex, "https://www.geoguessr.com.evil.com/"));
+    }
+
+    #[test]
+    fn match_pattern_exact_host_and_path() {
+        let regex = match_pattern_to_regex("https://www.geoguessr.com/maps/*").unwrap();
+        assert!(rule_matches(&regex, "https://www.geoguessr.com/maps/world"));
+        assert!(!rule_matches(&regex, "https://www.geoguessr.com/duels"));
+        assert!(!rule_matches(&regex, "http://www.geoguessr.com/maps/world"));
+    }
+
+    #[test]
+    fn match_pattern_all_urls() {
+        let regex = match_pattern_to_regex("<all_urls>").unwrap();
+        assert!(rule_matches(&regex, "https://www.geoguessr.com/"));
+        assert!(rule_matches(&regex, "file:///home/user/page.html"));
+        assert!(!rule_matches(&regex, "about:blank"));
+    }
+
+    #[test]
+    fn match_pattern_rejects_invalid() {
+        assert_eq!(match_pattern_to_regex("www.geoguessr.com/*"), None);
+        assert_eq!(match_pattern_to_regex("not a pattern"), None);
+    }
+
+    #[test]
+    fn include_glob_and_tld() {
+        let regex = include_pattern_to_regex("https://www.geoguessr.tld/*");
+        assert!(rule_matches(&regex, "https://www.geoguessr.com/game"));
+        assert!(rule_matches(&regex, "https://www.geoguessr.co.uk/game"));
+        assert!(!rule_matches(&regex, "https://www.geoguessrxcom/game"));
+
+        let regex = include_pattern_to_regex("*geoguessr.com/duels*");
+        assert!(rule_matches(&regex, "https://www.geoguessr.com/duels/123"));
+        assert!(!rule_matches(&regex, "https://www.geoguessr.com/maps"));
+    }
+
+    #[test]
+    fn include_regex() {
+        let regex = include_pattern_to_regex(r"/^https:\/\/www\.geoguessr\.com\/(duels|team-duels)/");
+        assert_eq!(regex, r"^https:\/\/www\.geoguessr\.com\/(duels|team-duels)");
+        assert!(rule_matches(&regex, "https://www.geoguessr.com/team-duels/1"));
+        assert!(!rule_matches(&regex, "https://www.geoguessr.com/maps"));
+    }
+
+    #[test]
+    fn url_rules_without_declarations_match_everything() {
+        let rules = script_url_rules(&script_with_rules(&[], &[]));
+        assert_eq!(rules["include"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn url_rules_all_invalid_match_nothing() {
+        let rules = script_url_rules(&script_with_rules(&["not a pattern", "geoguessr.com"], &[]));
+        assert_eq!(rules["include"], serde_json::json!(["(?!)"]));
+    }
+
+    #[test]
+    fn url_rules_keep_valid_ones() {
+        let rules = script_url_rules(&script_with_rules(&["not a pattern"], &["https://www.geoguessr.com/*"]));
+        assert_eq!(rules["include"], serde_json::json!(["^https:\\/\\/www\\.geoguessr\\.com\\/.*$"]));
+    }
+
+    // Expected values are what the bootstrap script's hmacSha256 (with SHA256_JS) returns for
+    // the same input, so a hot reload's stored scripts pass its check
+    #[test]
+    fn hmac_sha256_matches_bootstrap() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hmac_sha256("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // Non-ASCII message over several blocks
+        assert_eq!(
+            hmac_sha256("short", &"é".repeat(10000)),
+            "5c4501dbbcfe809272a85409097b2f3350a2127a39fe0e5ea10b28be6880cb1b"
+        );
+        // Key longer than a block is hashed first
+        assert_eq!(
+            hmac_sha256(&"k".repeat(100), ""),
+            "d46761768ee3c864fc76c08b3d73c8092853ae3fefa01b990963dfa2d56e7334"
+        );
+    }
+
+    const HELLO_SHA256_HEX: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+    const HELLO_SHA256_BASE64: &str = "LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=";
+    const HELLO_SHA384_BASE64_URL_SAFE: &str = "WeF0h3dEjGnea4ANejO7-5_xtGPkQ1TDVTvNucZm-pASWjx5-QOXvfX2oT3oKGhP";
+
+    #[test]
+    fn integrity_hashes_formats() {
+        let hashes = integrity_hashes(&format!("https://example.com/a.js#sha256={}", HELLO_SHA256_HEX));
+        assert_eq!(hashes.len(), 1);
+        assert_eq!(hashes[0].0, "sha256");
+        assert_eq!(hashes[0].2, <sha2::Sha256 as sha2::Digest>::digest(b"hello").to_vec());
+
+        let hashes = integrity_hashes(&format!("https://example.com/a.js#sha256-{}", HELLO_SHA256_BASE64));
+        assert_eq!(hashes[0].2, <sha2::Sha256 as sha2::Digest>::digest(b"hello").to_vec());
+
+        let hashes = integrity_hashes(&format!("https://example.com/a.js#SHA384={}", HELLO_SHA384_BASE64_URL_SAFE));
+        assert_eq!(hashes[0].0, "sha384");
+        assert_eq!(hashes[0].2, <sha2::Sha384 as sha2::Digest>::digest(b"hello").to_vec());
+    }
+
+    #[test]
+    fn integrity_hashes_lists() {
+        let url = format!(
+            "https://example.com/a.js#md5=abc,sha256={};sha384-{}",
+            HELLO_SHA256_HEX, HELLO_SHA384_BASE64_URL_SAFE
+        );
+        let algorithms: Vec<_> = integrity_hashes(&url).iter().map(|(algorithm, _, _)| *algorithm).collect();
+        assert_eq!(algorithms, ["sha256", "sha384"]);
+
+        assert!(integrity_hashes("https://example.com/a.js").is_empty());
+        assert!(integrity_hashes("https://example.com/a.js#section").is_empty());
+    }
+
+    #[test]
+    fn verify_integrity_checks_content() {
+        assert!(verify_integrity("https://example.com/a.js", b"anything").is_ok());
+        let url = format!("https://example.com/a.js#sha256={}", HELLO_SHA256_HEX);
+        assert!(verify_integrity(&url, b"hello").is_ok());
+        assert!(verify_integrity(&url, b"hello!").is_err());
+
+        // Any one hash of the algorithm matching is enough
+        let url = format!("https://example.com/a.js#sha256={},sha256-{}", "0".repeat(64), HELLO_SHA256_BASE64);
+        assert!(verify_integrity(&url, b"hello").is_ok());
+    }
+
+    #[test]
+    fn verify_integrity_uses_strongest_algorithm() {
+        // A matching weaker hash doesn't make up for a wrong stronger one
+        let url = format!("https://example.com/a.js#sha256={},sha512={}", HELLO_SHA256_HEX, "0".repeat(128));
+        assert!(verify_integrity(&url, b"hello").is_err());
+
+        // And a wrong weaker hash is ignored
+        let url = format!("https://example.com/a.js#sha256={},sha384={}", "0".repeat(64), HELLO_SHA384_BASE64_URL_SAFE);
+        assert!(verify_integrity(&url, b"hello").is_ok());
+    }
 }
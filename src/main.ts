@@ -1,4 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { getCurrentWebview } from "@tauri-apps/api/webview";
 
 interface UserScript {
   id: string;
@@ -10,22 +12,311 @@ interface UserScript {
   version?: string;
   description?: string;
   author?: string;
+  namespace?: string;
   requires?: string[];
+  resources?: { name: string; url: string }[];
+  matches?: string[];
+  includes?: string[];
+  excludes?: string[];
+  exclude_matches?: string[];
+  run_at?: string;
+  grants?: string[];
+  connects?: string[];
+  user_connects?: string[];
+  local_path?: string;
   last_updated?: number;
   last_fetch_error?: string;
+  pending_update?: PendingUpdate | null;
+  pinned?: boolean;
+}
+
+interface ScriptRevision {
+  id: string;
+  code: string;
+  name: string;
+  version?: string;
+  saved_at: number;
+  reason: string;
+}
+
+interface PendingUpdate {
+  code: string;
+  version?: string;
+  fetched_at: number;
+}
+
+interface DiffLine {
+  tag: "equal" | "delete" | "insert";
+  old_line?: number;
+  new_line?: number;
+  text: string;
+}
+
+interface ScriptUpdateDiff {
+  current_version?: string;
+  new_version?: string;
+  metadata: { key: string; added: string[]; removed: string[] }[];
+  refreshed_requires: string[];
+  hunks: DiffLine[][];
+}
+
+interface MenuCommand {
+  window: string;
+  script_id: string;
+  command_id: string;
+  caption: string;
+}
+
+interface GameInstance {
+  label: string;
+  name: string;
+  profile?: string;
+  scripts?: string[] | null;
+}
+
+interface AppSettings {
+  download_location: "downloads" | "data_dir";
 }
 
 let scripts: UserScript[] = [];
+let menuCommands: MenuCommand[] = [];
+let editingScriptId: string | null = null;
+let instances: GameInstance[] = [];
+let editingInstanceLabel: string | null = null;
+let reviewingScriptId: string | null = null;
+let historyScriptId: string | null = null;
+
+const MAIN_WINDOW_LABEL = "geoguessr";
 
 async function loadScripts() {
   try {
     scripts = await invoke("get_scripts");
     renderScriptsList();
+    renderInstancesList();
   } catch (e) {
     console.error("Failed to load scripts:", e);
   }
 }
 
+async function loadMenuCommands() {
+  try {
+    menuCommands = await invoke("get_menu_commands");
+    renderScriptsList();
+  } catch (e) {
+    console.error("Failed to load menu commands:", e);
+  }
+}
+
+async function runMenuCommand(command: MenuCommand) {
+  try {
+    await invoke("run_menu_command", {
+      window: command.window,
+      scriptId: command.script_id,
+      commandId: command.command_id,
+    });
+  } catch (e) {
+    console.error("Failed to run menu command:", e);
+  }
+}
+
+async function loadInstances() {
+  try {
+    instances = await invoke("get_instances");
+    renderInstancesList();
+  } catch (e) {
+    console.error("Failed to load windows:", e);
+  }
+}
+
+function renderInstancesList() {
+  const instancesList = document.getElementById("instances-list");
+  if (!instancesList) return;
+
+  instancesList.innerHTML = "";
+
+  instances.forEach((instance) => {
+    const instanceItem = document.createElement("div");
+    instanceItem.className = "script-item";
+
+    const nameSpan = document.createElement("span");
+    nameSpan.className = "script-name";
+    nameSpan.textContent = instance.name;
+
+    // Only count scripts that still exist
+    const summary = document.createElement("span");
+    summary.className = "instance-scripts-summary";
+    if (instance.scripts) {
+      const count = instance.scripts.filter((id) => scripts.some((s) => s.id === id)).length;
+      summary.textContent = count === 1 ? "1 script" : `${count} scripts`;
+    } else {
+      summary.textContent = "All scripts";
+    }
+
+    const editBtn = document.createElement("button");
+    editBtn.className = "btn-edit";
+    editBtn.textContent = "✎";
+    editBtn.title = "Rename or choose scripts";
+    editBtn.addEventListener("click", () => openInstanceEditor(instance));
+
+    const openBtn = document.createElement("button");
+    openBtn.className = "btn-edit";
+    openBtn.textContent = "▶";
+    openBtn.title = "Open window";
+    openBtn.addEventListener("click", () => openGeoGuessr(instance.label));
+
+    instanceItem.appendChild(nameSpan);
+    instanceItem.appendChild(summary);
+    instanceItem.appendChild(editBtn);
+    instanceItem.appendChild(openBtn);
+
+    // The main window uses the default browser profile and can't be removed
+    if (instance.label !== MAIN_WINDOW_LABEL) {
+      const deleteBtn = document.createElement("button");
+      deleteBtn.className = "btn-delete";
+      deleteBtn.textContent = "×";
+      deleteBtn.title = "Remove window and its login";
+      deleteBtn.addEventListener("click", () => deleteInstance(instance.label));
+      instanceItem.appendChild(deleteBtn);
+    }
+
+    instancesList.appendChild(instanceItem);
+  });
+}
+
+async function createInstance() {
+  const nameInput = document.getElementById("new-instance-name") as HTMLInputElement | null;
+  if (!nameInput || !nameInput.value.trim()) return;
+
+  try {
+    const instance = await invoke("create_instance", { name: nameInput.value }) as GameInstance;
+    instances.push(instance);
+    nameInput.value = "";
+    renderInstancesList();
+  } catch (e) {
+    alert("Adding window failed: " + e);
+  }
+}
+
+async function deleteInstance(label: string) {
+  try {
+    await invoke("delete_instance", { label });
+    instances = instances.filter((i) => i.label !== label);
+    renderInstancesList();
+  } catch (e) {
+    console.error("Failed to remove window:", e);
+  }
+}
+
+function openInstanceEditor(instance: GameInstance) {
+  const instanceModal = document.getElementById("instance-modal");
+  const instanceTitle = document.getElementById("instance-title");
+  const nameInput = document.getElementById("instance-name") as HTMLInputElement | null;
+  const allScripts = document.getElementById("instance-all-scripts") as HTMLInputElement | null;
+  const scriptsContainer = document.getElementById("instance-scripts");
+  if (!instanceModal || !nameInput || !allScripts || !scriptsContainer) return;
+
+  editingInstanceLabel = instance.label;
+  if (instanceTitle) instanceTitle.textContent = instance.name;
+  nameInput.value = instance.name;
+  allScripts.checked = !instance.scripts;
+
+  // Scripts disabled in the manager don't run in any window, whether checked here or not
+  scriptsContainer.innerHTML = "";
+  [...scripts].sort((a, b) => a.order - b.order).forEach((script) => {
+    const option = document.createElement("label");
+    option.className = "instance-script-option";
+
+    const checkbox = document.createElement("input");
+    checkbox.type = "checkbox";
+    checkbox.dataset.scriptId = script.id;
+    checkbox.checked = instance.scripts ? instance.scripts.includes(script.id) : script.enabled;
+    checkbox.disabled = allScripts.checked;
+
+    option.appendChild(checkbox);
+    option.appendChild(document.createTextNode(script.enabled ? script.name : `${script.name} (disabled)`));
+    scriptsContainer.appendChild(option);
+  });
+
+  document.getElementById("instance-error")?.classList.add("hidden");
+  instanceModal.classList.remove("hidden");
+}
+
+function closeInstanceEditor() {
+  editingInstanceLabel = null;
+  document.getElementById("instance-modal")?.classList.add("hidden");
+}
+
+async function saveInstanceEditor() {
+  const nameInput = document.getElementById("instance-name") as HTMLInputElement | null;
+  const allScripts = document.getElementById("instance-all-scripts") as HTMLInputElement | null;
+  const instanceError = document.getElementById("instance-error");
+  if (!editingInstanceLabel || !nameInput || !allScripts) return;
+
+  const checked = document.querySelectorAll<HTMLInputElement>("#instance-scripts input:checked");
+  const selectedScripts = allScripts.checked
+    ? null
+    : Array.from(checked).map((checkbox) => checkbox.dataset.scriptId as string);
+
+  try {
+    const updated = await invoke("update_instance", {
+      label: editingInstanceLabel,
+      name: nameInput.value,
+      scripts: selectedScripts,
+    }) as GameInstance;
+    const index = instances.findIndex((i) => i.label === updated.label);
+    if (index !== -1) {
+      instances[index] = updated;
+    }
+    closeInstanceEditor();
+    renderInstancesList();
+  } catch (e) {
+    if (instanceError) {
+      instanceError.textContent = String(e);
+      instanceError.classList.remove("hidden");
+    }
+  }
+}
+
+async function loadSettings() {
+  const downloadLocation = document.getElementById("download-location") as HTMLSelectElement | null;
+  if (!downloadLocation) return;
+
+  try {
+    const settings = await invoke("get_settings") as AppSettings;
+    downloadLocation.value = settings.download_location;
+  } catch (e) {
+    console.error("Failed to load settings:", e);
+  }
+
+  downloadLocation.addEventListener("change", async () => {
+    const settings: AppSettings = {
+      download_location: downloadLocation.value as AppSettings["download_location"],
+    };
+    try {
+      await invoke("update_settings", { settings });
+    } catch (e) {
+      console.error("Failed to save settings:", e);
+    }
+  });
+}
+
+// Import userscript files dropped onto the manager window
+async function importDroppedScripts(paths: string[]) {
+  const failures: string[] = [];
+  for (const path of paths.filter((p) => p.toLowerCase().endsWith(".js"))) {
+    try {
+      await invoke("add_script_from_file", { path });
+    } catch (e) {
+      console.error("Failed to import script:", e);
+      failures.push(String(e));
+    }
+  }
+  await loadScripts();
+  if (failures.length > 0) {
+    alert("Import failed: " + failures.join("\n"));
+  }
+}
+
 function renderScriptsList() {
   const scriptsList = document.getElementById("scripts-list");
   if (!scriptsList) return;
@@ -35,7 +326,7 @@ function renderScriptsList() {
   if (scripts.length === 0) {
     const emptyMessage = document.createElement("p");
     emptyMessage.className = "empty-message";
-    emptyMessage.textContent = "No scripts added yet";
+    emptyMessage.textContent = "No scripts added yet. Add one from a URL or drop a .user.js file here.";
     scriptsList.appendChild(emptyMessage);
     return;
   }
@@ -74,6 +365,10 @@ function renderScriptsList() {
     nameSpan.className = "script-name";
     nameSpan.textContent = script.name;
 
+    scriptItem.appendChild(orderControls);
+    scriptItem.appendChild(checkbox);
+    scriptItem.appendChild(nameSpan);
+
     // Add refresh button for URL-based scripts
     if (script.url) {
       const refreshBtn = document.createElement("button");
@@ -90,14 +385,53 @@ function renderScriptsList() {
           refreshBtn.disabled = false;
         }
       });
-      scriptItem.appendChild(orderControls);
-      scriptItem.appendChild(checkbox);
-      scriptItem.appendChild(nameSpan);
       scriptItem.appendChild(refreshBtn);
-    } else {
-      scriptItem.appendChild(orderControls);
-      scriptItem.appendChild(checkbox);
-      scriptItem.appendChild(nameSpan);
+    }
+
+    const editBtn = document.createElement("button");
+    editBtn.className = "btn-edit";
+    editBtn.textContent = "✎";
+    editBtn.title = "Edit code";
+    editBtn.addEventListener("click", () => openEditor(script));
+    scriptItem.appendChild(editBtn);
+
+    const historyBtn = document.createElement("button");
+    historyBtn.className = "btn-history";
+    historyBtn.textContent = "🕘";
+    historyBtn.title = "Version history";
+    historyBtn.addEventListener("click", () => openHistory(script));
+    scriptItem.appendChild(historyBtn);
+
+    // Pinned scripts are left out of automatic updates
+    if (script.pinned) {
+      const pinBtn = document.createElement("button");
+      pinBtn.className = "btn-pin";
+      pinBtn.textContent = "📌";
+      pinBtn.title = "Pinned to this version (click to allow automatic updates)";
+      pinBtn.addEventListener("click", () => unpinScript(script.id));
+      scriptItem.appendChild(pinBtn);
+    }
+
+    // Link to a file on disk, which the app then watches and reloads on save
+    const linkBtn = document.createElement("button");
+    linkBtn.className = script.local_path ? "btn-link linked" : "btn-link";
+    linkBtn.textContent = "⛓";
+    linkBtn.title = script.local_path
+      ? `Linked to ${script.local_path} (click to unlink)`
+      : "Link to a file on disk and reload on every save";
+    linkBtn.addEventListener("click", () => toggleScriptLink(script));
+    scriptItem.appendChild(linkBtn);
+
+    // An update was found and waits for review before it runs
+    if (script.pending_update) {
+      const updateBtn = document.createElement("button");
+      updateBtn.className = "btn-update";
+      updateBtn.textContent = "⬆";
+      updateBtn.title = script.pending_update.version
+        ? `Review update to ${script.pending_update.version}`
+        : "Review update";
+      updateBtn.addEventListener("click", () => openUpdateReview(script));
+      scriptItem.appendChild(updateBtn);
     }
 
     // Add error indicator
@@ -109,6 +443,21 @@ function renderScriptsList() {
       scriptItem.appendChild(errorIcon);
     }
 
+    // Add commands registered by the script with GM_registerMenuCommand
+    const commands = menuCommands.filter((c) => c.script_id === script.id);
+    if (commands.length > 0) {
+      const commandsContainer = document.createElement("div");
+      commandsContainer.className = "menu-commands";
+      commands.forEach((command) => {
+        const commandBtn = document.createElement("button");
+        commandBtn.className = "btn-menu-command";
+        commandBtn.textContent = command.caption;
+        commandBtn.addEventListener("click", () => runMenuCommand(command));
+        commandsContainer.appendChild(commandBtn);
+      });
+      scriptItem.appendChild(commandsContainer);
+    }
+
     const deleteBtn = document.createElement("button");
     deleteBtn.className = "btn-delete";
     deleteBtn.textContent = "×";
@@ -163,16 +512,276 @@ async function refreshScript(id: string) {
       scripts[index] = updated;
     }
     renderScriptsList();
-    await reloadScripts();
+    // A newer version only runs once it's accepted
+    if (updated.pending_update) {
+      await openUpdateReview(updated);
+    }
   } catch (e) {
+    // The failure is also kept on the script, show its warning
+    await loadScripts();
     console.error("Failed to refresh script:", e);
     throw e;
   }
 }
 
-async function openGeoGuessr() {
+// Show what a pending update changes before it's accepted
+async function openUpdateReview(script: UserScript) {
+  const updateModal = document.getElementById("update-modal");
+  const updateTitle = document.getElementById("update-title");
+  const updateVersion = document.getElementById("update-version");
+  const updateMetadata = document.getElementById("update-metadata");
+  const updateDiff = document.getElementById("update-diff");
+  if (!updateModal || !updateVersion || !updateMetadata || !updateDiff) return;
+
+  let diff: ScriptUpdateDiff;
+  try {
+    diff = await invoke("get_script_update_diff", { id: script.id }) as ScriptUpdateDiff;
+  } catch (e) {
+    alert("Loading the update failed: " + e);
+    return;
+  }
+
+  reviewingScriptId = script.id;
+  if (updateTitle) updateTitle.textContent = `Update ${script.name}`;
+  updateVersion.textContent = `Version ${diff.current_version ?? "unknown"} → ${diff.new_version ?? "unknown"}`;
+
+  updateMetadata.innerHTML = "";
+  diff.metadata.forEach((change) => {
+    // New permissions and code sources are what a reviewer should look at first
+    const sensitive = ["grant", "connect", "require"].includes(change.key);
+    change.added.forEach((value) => {
+      const item = document.createElement("li");
+      item.className = sensitive ? "metadata-added metadata-warning" : "metadata-added";
+      item.textContent = `+ @${change.key} ${value}`;
+      updateMetadata.appendChild(item);
+    });
+    change.removed.forEach((value) => {
+      const item = document.createElement("li");
+      item.className = "metadata-removed";
+      item.textContent = `- @${change.key} ${value}`;
+      updateMetadata.appendChild(item);
+    });
+  });
+  // Required files that changed on their server run along with the script once accepted
+  diff.refreshed_requires.forEach((url) => {
+    const item = document.createElement("li");
+    item.className = "metadata-added metadata-warning";
+    item.textContent = `~ @require ${url} (new content)`;
+    updateMetadata.appendChild(item);
+  });
+
+  updateDiff.innerHTML = "";
+  diff.hunks.forEach((hunk) => {
+    const hunkElement = document.createElement("div");
+    hunkElement.className = "diff-hunk";
+    hunk.forEach((line) => {
+      const lineElement = document.createElement("div");
+      lineElement.className = `diff-line diff-${line.tag}`;
+      const marker = line.tag === "insert" ? "+" : line.tag === "delete" ? "-" : " ";
+      const lineNumber = line.new_line ?? line.old_line ?? "";
+      lineElement.textContent = `${String(lineNumber).padStart(5)} ${marker} ${line.text}`;
+      hunkElement.appendChild(lineElement);
+    });
+    updateDiff.appendChild(hunkElement);
+  });
+
+  document.getElementById("update-error")?.classList.add("hidden");
+  updateModal.classList.remove("hidden");
+}
+
+function closeUpdateReview() {
+  reviewingScriptId = null;
+  document.getElementById("update-modal")?.classList.add("hidden");
+}
+
+async function resolveUpdate(accept: boolean) {
+  const updateError = document.getElementById("update-error");
+  if (!reviewingScriptId) return;
+
+  try {
+    const command = accept ? "accept_script_update" : "reject_script_update";
+    const updated = await invoke(command, { id: reviewingScriptId }) as UserScript;
+    const index = scripts.findIndex((s) => s.id === updated.id);
+    if (index !== -1) {
+      scripts[index] = updated;
+    }
+    closeUpdateReview();
+    renderScriptsList();
+    if (accept) {
+      await reloadScripts();
+    }
+  } catch (e) {
+    if (updateError) {
+      updateError.textContent = String(e);
+      updateError.classList.remove("hidden");
+    }
+  }
+}
+
+async function openHistory(script: UserScript) {
+  const historyModal = document.getElementById("history-modal");
+  const historyTitle = document.getElementById("history-title");
+  const historyList = document.getElementById("history-list");
+  const historyPin = document.getElementById("history-pin") as HTMLInputElement | null;
+  if (!historyModal || !historyList) return;
+
+  let revisions: ScriptRevision[];
+  try {
+    revisions = await invoke("get_script_history", { id: script.id }) as ScriptRevision[];
+  } catch (e) {
+    alert("Loading the history failed: " + e);
+    return;
+  }
+
+  historyScriptId = script.id;
+  if (historyTitle) historyTitle.textContent = `${script.name} History`;
+  if (historyPin) historyPin.checked = !!script.pinned;
+
+  historyList.innerHTML = "";
+  if (revisions.length === 0) {
+    const emptyMessage = document.createElement("p");
+    emptyMessage.className = "empty-message";
+    emptyMessage.textContent = "No earlier versions yet.";
+    historyList.appendChild(emptyMessage);
+  }
+
+  revisions.forEach((revision) => {
+    const item = document.createElement("div");
+    item.className = "history-item";
+
+    const label = document.createElement("span");
+    const version = revision.version ? `v${revision.version}` : "no version";
+    label.textContent = `${new Date(revision.saved_at * 1000).toLocaleString()} · ${version} · replaced by ${revision.reason}`;
+
+    const restoreBtn = document.createElement("button");
+    restoreBtn.className = "btn-secondary";
+    restoreBtn.textContent = "Restore";
+    restoreBtn.addEventListener("click", () => restoreRevision(revision.id));
+
+    item.appendChild(label);
+    item.appendChild(restoreBtn);
+    historyList.appendChild(item);
+  });
+
+  document.getElementById("history-error")?.classList.add("hidden");
+  historyModal.classList.remove("hidden");
+}
+
+function closeHistory() {
+  historyScriptId = null;
+  document.getElementById("history-modal")?.classList.add("hidden");
+}
+
+async function restoreRevision(revisionId: string) {
+  const historyPin = document.getElementById("history-pin") as HTMLInputElement | null;
+  const historyError = document.getElementById("history-error");
+  if (!historyScriptId) return;
+
+  try {
+    const updated = await invoke("rollback_script", {
+      id: historyScriptId,
+      revisionId,
+      pin: historyPin?.checked ?? false,
+    }) as UserScript;
+    const index = scripts.findIndex((s) => s.id === updated.id);
+    if (index !== -1) {
+      scripts[index] = updated;
+    }
+    closeHistory();
+    renderScriptsList();
+    await reloadScripts();
+  } catch (e) {
+    if (historyError) {
+      historyError.textContent = String(e);
+      historyError.classList.remove("hidden");
+    }
+  }
+}
+
+async function unpinScript(id: string) {
+  try {
+    const updated = await invoke("set_script_pinned", { id, pinned: false }) as UserScript;
+    const index = scripts.findIndex((s) => s.id === id);
+    if (index !== -1) {
+      scripts[index] = updated;
+    }
+    renderScriptsList();
+  } catch (e) {
+    console.error("Failed to unpin script:", e);
+  }
+}
+
+function openEditor(script: UserScript) {
+  const editorModal = document.getElementById("editor-modal");
+  const editorTitle = document.getElementById("editor-title");
+  const editorCode = document.getElementById("editor-code") as HTMLTextAreaElement | null;
+  if (!editorModal || !editorCode) return;
+
+  editingScriptId = script.id;
+  if (editorTitle) editorTitle.textContent = script.name;
+  editorCode.value = script.code;
+  document.getElementById("editor-error")?.classList.add("hidden");
+  editorModal.classList.remove("hidden");
+  editorCode.focus();
+}
+
+function closeEditor() {
+  editingScriptId = null;
+  document.getElementById("editor-modal")?.classList.add("hidden");
+}
+
+async function saveEditor() {
+  const editorCode = document.getElementById("editor-code") as HTMLTextAreaElement | null;
+  const editorError = document.getElementById("editor-error");
+  if (!editingScriptId || !editorCode) return;
+
   try {
-    await invoke("open_geoguessr");
+    const updated = await invoke("update_script_code", { id: editingScriptId, code: editorCode.value }) as UserScript;
+    const index = scripts.findIndex((s) => s.id === updated.id);
+    if (index !== -1) {
+      scripts[index] = updated;
+    }
+    closeEditor();
+    renderScriptsList();
+    await reloadScripts();
+  } catch (e) {
+    if (editorError) {
+      editorError.textContent = String(e);
+      editorError.classList.remove("hidden");
+    }
+  }
+}
+
+async function createScript() {
+  try {
+    const newScript = await invoke("create_script") as UserScript;
+    scripts.push(newScript);
+    renderScriptsList();
+    openEditor(newScript);
+  } catch (e) {
+    console.error("Failed to create script:", e);
+  }
+}
+
+async function toggleScriptLink(script: UserScript) {
+  try {
+    if (script.local_path) {
+      await invoke("unlink_script_file", { id: script.id });
+    } else {
+      const linked = await invoke("link_script_file", { id: script.id });
+      // null when the file picker was cancelled
+      if (!linked) return;
+      await reloadScripts();
+    }
+    await loadScripts();
+  } catch (e) {
+    alert("Linking failed: " + e);
+  }
+}
+
+async function openGeoGuessr(label?: string) {
+  try {
+    await invoke("open_geoguessr", { label: label ?? null });
   } catch (e) {
     console.error("Failed to open GeoGuessr:", e);
   }
@@ -253,6 +862,46 @@ window.addEventListener("DOMContentLoaded", async () => {
     modal?.classList.remove("hidden");
   });
 
+  document.getElementById("new-script-btn")?.addEventListener("click", () => createScript());
+  document.getElementById("add-instance-btn")?.addEventListener("click", () => createInstance());
+  document.getElementById("new-instance-name")?.addEventListener("keydown", (e) => {
+    if (e.key === "Enter") createInstance();
+  });
+  document.getElementById("close-instance-modal")?.addEventListener("click", () => closeInstanceEditor());
+  document.getElementById("cancel-instance-btn")?.addEventListener("click", () => closeInstanceEditor());
+  document.getElementById("save-instance-btn")?.addEventListener("click", () => saveInstanceEditor());
+
+  // Picking scripts by hand only makes sense when the window doesn't run all of them
+  const instanceAllScripts = document.getElementById("instance-all-scripts") as HTMLInputElement | null;
+  if (instanceAllScripts) {
+    instanceAllScripts.addEventListener("change", () => {
+      document.querySelectorAll<HTMLInputElement>("#instance-scripts input").forEach((checkbox) => {
+        checkbox.disabled = instanceAllScripts.checked;
+      });
+    });
+  }
+  document.getElementById("close-editor")?.addEventListener("click", () => closeEditor());
+  document.getElementById("cancel-editor-btn")?.addEventListener("click", () => closeEditor());
+  document.getElementById("save-editor-btn")?.addEventListener("click", () => saveEditor());
+  document.getElementById("close-update-modal")?.addEventListener("click", () => closeUpdateReview());
+  document.getElementById("close-history-modal")?.addEventListener("click", () => closeHistory());
+  document.getElementById("reject-update-btn")?.addEventListener("click", () => resolveUpdate(false));
+  document.getElementById("accept-update-btn")?.addEventListener("click", () => resolveUpdate(true));
+
+  // Tab indents instead of leaving the editor, Ctrl/Cmd+S saves
+  const editorCode = document.getElementById("editor-code") as HTMLTextAreaElement | null;
+  if (editorCode) {
+    editorCode.addEventListener("keydown", (e) => {
+      if (e.key === "Tab") {
+        e.preventDefault();
+        editorCode.setRangeText("    ", editorCode.selectionStart, editorCode.selectionEnd, "end");
+      } else if (e.key === "s" && (e.ctrlKey || e.metaKey)) {
+        e.preventDefault();
+        saveEditor();
+      }
+    });
+  }
+
   closeModal?.addEventListener("click", () => {
     modal?.classList.add("hidden");
     scriptUrlInput.value = "";
@@ -302,6 +951,16 @@ window.addEventListener("DOMContentLoaded", async () => {
   });
 
   await loadScripts();
+  await loadInstances();
+  await loadMenuCommands();
+  await listen("gm_menu_commands_changed", () => loadMenuCommands());
+  await listen("scripts_changed", () => loadScripts());
+  await loadSettings();
+  await getCurrentWebview().onDragDropEvent((event) => {
+    if (event.payload.type === "drop") {
+      importDroppedScripts(event.payload.paths);
+    }
+  });
 
   // Log data directory location
   try {
@@ -315,7 +974,7 @@ window.addEventListener("DOMContentLoaded", async () => {
   try {
     const updateCount = await invoke("auto_update_scripts") as number;
     if (updateCount > 0) {
-      console.log(`Updated ${updateCount} scripts`);
+      console.log(`${updateCount} script updates waiting for review`);
       await loadScripts();
     }
   } catch (e) {